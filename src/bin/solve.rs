@@ -0,0 +1,58 @@
+use std::env;
+use std::process::ExitCode;
+
+use diamond_rust::campaign::Campaign;
+use diamond_rust::campaign::CAMPAIGN_PATH;
+use diamond_rust::level::LevelGrid;
+use diamond_rust::rules::TileRules;
+use diamond_rust::rules::TILE_RULES_PATH;
+use diamond_rust::solver::solve;
+
+// Solves the given level files, or all the campaign levels when run without arguments,
+// and prints the shortest solutions. Exits with 1 if any level cannot be finished.
+fn main() -> ExitCode {
+    let mut paths: Vec<String> = env::args().skip(1).collect();
+    if paths.is_empty() {
+        paths = Campaign::load(CAMPAIGN_PATH).levels;
+    }
+
+    let rules = TileRules::load(TILE_RULES_PATH);
+    let mut failed = false;
+    for path in paths.iter() {
+        let report = solve(&LevelGrid::load(path), &rules);
+        for (x, y) in report.movable_stones.iter() {
+            eprintln!(
+                "{}: warning: the stone on cell ({}, {}) can move, but is taken as a wall",
+                path, x, y
+            );
+        }
+        if let Some(error) = report.error {
+            failed = true;
+            eprintln!("{}: cannot be searched, {}", path, error);
+            continue;
+        }
+        match report.solution {
+            Some(solution) => {
+                let moves: Vec<String> = solution.iter().map(|action| action.to_string()).collect();
+                println!("{}: solved in {} moves", path, solution.len());
+                println!("  {}", moves.join(" "));
+            }
+            None => {
+                failed = true;
+                eprintln!(
+                    "{}: unsolvable, {} of {} diamonds can be collected",
+                    path, report.reachable_diamonds, report.diamonds
+                );
+                if report.exit_reachable == Some(false) {
+                    eprintln!("{}: the exit cannot be reached", path);
+                }
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
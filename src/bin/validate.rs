@@ -0,0 +1,44 @@
+use std::env;
+use std::process::ExitCode;
+
+use diamond_rust::campaign::Campaign;
+use diamond_rust::campaign::CAMPAIGN_PATH;
+use diamond_rust::rules::TileRules;
+use diamond_rust::rules::TILE_RULES_PATH;
+use diamond_rust::validate::validate_file;
+
+// Checks the given level files, or all the campaign levels when run without arguments.
+// Exits with 1 if any problem other than a warning was found.
+fn main() -> ExitCode {
+    let mut paths: Vec<String> = env::args().skip(1).collect();
+    if paths.is_empty() {
+        paths = Campaign::load(CAMPAIGN_PATH).levels;
+    }
+
+    let rules = TileRules::load(TILE_RULES_PATH);
+    let mut failed = false;
+    for path in paths.iter() {
+        match validate_file(path, &rules) {
+            Ok(diagnostics) => {
+                for diagnostic in diagnostics.iter() {
+                    eprintln!("{}", diagnostic.report(path));
+                }
+                if diagnostics.iter().all(|diagnostic| diagnostic.warning) {
+                    println!("{}: ok", path);
+                } else {
+                    failed = true;
+                }
+            }
+            Err(error) => {
+                failed = true;
+                eprintln!("{}: {}", path, error);
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
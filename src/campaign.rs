@@ -0,0 +1,195 @@
+use bevy::prelude::*;
+use std::fs;
+
+use crate::game::replace_level;
+use crate::game::GameObject;
+use crate::game::GridPos;
+use crate::game::LevelEntity;
+use crate::level::LevelGrid;
+use crate::level::TileKind;
+use crate::player::GameSaver;
+use crate::rules::Effect;
+use crate::rules::TileRules;
+use crate::rules::TILE_RULES_PATH;
+use crate::sim::Game;
+use crate::sim::GameEvent;
+use crate::sim::PlayerState;
+use crate::sim::TICK_SECONDS;
+use crate::textures::spawn_from_textures;
+use crate::textures::CharacterTextures;
+
+pub const CAMPAIGN_PATH: &str = "assets/campaign.txt";
+pub const EXIT_IDX: usize = 9;
+
+pub struct CampaignPlugin;
+
+// Ordered list of level files and the progress on the one being played.
+pub struct Campaign {
+    pub levels: Vec<String>,
+    pub current: usize,
+    pub completed: bool,
+    diamonds_at_level_start: usize,
+}
+
+#[derive(Component)]
+pub struct ExitDetect;
+
+impl Campaign {
+    // Reads the level list, one map path per line.
+    pub fn load(path: &str) -> Campaign {
+        let source = fs::read_to_string(path).expect("Couldn't open campaign asset!");
+        let levels: Vec<String> = source
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| line.to_string())
+            .collect();
+        assert!(!levels.is_empty(), "{}: the campaign has no levels", path);
+
+        Campaign::new(levels)
+    }
+
+    pub fn new(levels: Vec<String>) -> Campaign {
+        Campaign {
+            levels,
+            current: 0,
+            completed: false,
+            diamonds_at_level_start: 0,
+        }
+    }
+
+    pub fn current_level(&self) -> &str {
+        &self.levels[self.current]
+    }
+}
+
+impl Plugin for CampaignPlugin {
+    // Plays the Campaign resource if the app has one, the default campaign otherwise.
+    fn build(&self, app: &mut App) {
+        if !app.world.contains_resource::<Campaign>() {
+            app.insert_resource(Campaign::load(CAMPAIGN_PATH));
+        }
+
+        app.add_startup_system_to_stage(StartupStage::PreStartup, load_campaign)
+            .add_system(spawn_exits)
+            .add_system(exit_reached.after("step_game"));
+    }
+}
+
+fn load_campaign(mut commands: Commands, campaign: Res<Campaign>) {
+    let level = LevelGrid::load(campaign.current_level());
+    announce_level(&level);
+    commands.insert_resource(Game::new(
+        &level,
+        PlayerState::default(),
+        TileRules::load(TILE_RULES_PATH),
+    ));
+    commands.insert_resource(level);
+}
+
+fn announce_level(level: &LevelGrid) {
+    if level.info.author.is_empty() {
+        info!("Level: {}", level.info.name);
+    } else {
+        info!("Level: {} by {}", level.info.name, level.info.author);
+    }
+}
+
+// Places level exits on the map, basing on the loaded level
+fn spawn_exits(
+    mut commands: Commands,
+    texture: Res<CharacterTextures>,
+    level: Res<LevelGrid>,
+    game: Res<Game>,
+) {
+    if !level.is_changed() {
+        return;
+    }
+
+    let mut exits_storage = Vec::new();
+
+    for (id, object) in game.objects() {
+        if object.kind == TileKind::Exit {
+            let exit = spawn_from_textures(
+                &mut commands,
+                &texture,
+                EXIT_IDX,
+                GridPos::from(object.cell),
+                150.0,
+            );
+
+            commands
+                .entity(exit)
+                .insert(ExitDetect)
+                .insert(GameObject(id));
+            exits_storage.push(exit);
+        }
+    }
+
+    commands
+        .spawn()
+        .insert(Name::new("Exits"))
+        .insert(LevelEntity)
+        .insert(Transform::default())
+        .insert(GlobalTransform::default())
+        .push_children(&exits_storage);
+}
+
+// Completes the level when the player steps on an exit with enough diamonds
+// collected on this level, and builds the next one.
+fn exit_reached(
+    mut commands: Commands,
+    mut campaign: ResMut<Campaign>,
+    mut events: EventReader<GameEvent>,
+    game: Res<Game>,
+    level_entities: Query<Entity, With<LevelEntity>>,
+    level: Res<LevelGrid>,
+    saver: GameSaver,
+) {
+    let stepped_on_exit = events.iter().any(|event| {
+        matches!(event, GameEvent::Interacted(interactable, _) if interactable.effect == Effect::ReachExit)
+    });
+    if campaign.completed || !stepped_on_exit {
+        return;
+    }
+
+    let collected = game.player().diamonds - campaign.diamonds_at_level_start;
+    if collected < level.info.required_diamonds {
+        info!(
+            "The exit needs {} diamonds, {} collected",
+            level.info.required_diamonds, collected
+        );
+        return;
+    }
+
+    let level_time = (game.tick() as f64 * TICK_SECONDS) as f32;
+    match level.info.par_time {
+        Some(par_time) => info!(
+            "Level completed in {:.1}s (par {:.1}s)",
+            level_time, par_time
+        ),
+        None => info!("Level completed in {:.1}s", level_time),
+    }
+
+    if campaign.current + 1 == campaign.levels.len() {
+        campaign.completed = true;
+        info!("Campaign completed!");
+        return;
+    }
+
+    campaign.current += 1;
+    campaign.diamonds_at_level_start = game.player().diamonds;
+
+    // Keys are left behind, the rest of the player is carried over and saved,
+    // as the start of the next level is a save point.
+    let player = PlayerState {
+        keys: 0,
+        ..game.player().clone()
+    };
+    saver.save(&player);
+
+    let level = LevelGrid::load(campaign.current_level());
+    announce_level(&level);
+    let game = Game::new(&level, player, *game.rules());
+    replace_level(&mut commands, &level_entities, level, game);
+}
@@ -1,15 +1,89 @@
 use bevy::prelude::*;
 use bevy_inspector_egui::{RegisterInspectable, WorldInspectorPlugin};
+use std::fs;
+use std::time::SystemTime;
 
+use crate::campaign::Campaign;
+use crate::game::replace_level;
+use crate::game::LevelEntity;
+use crate::level::LevelGrid;
 use crate::player::Player;
+use crate::sim::Game;
 
-pub struct DebugPlugin;
+// How often (in seconds) the level file is checked for changes.
+pub const LEVEL_CHECK_INTERVAL: f32 = 0.5;
+
+// Development helpers, only added to debug builds: the egui inspector (which needs
+// a window) and the hot reload of the level file.
+pub struct DebugPlugin {
+    pub inspector: bool,
+}
 
 impl Plugin for DebugPlugin {
     fn build(&self, app: &mut App) {
-        if cfg!(debug_assertions) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+
+        if self.inspector {
             app.add_plugin(WorldInspectorPlugin::new())
                 .register_inspectable::<Player>();
         }
+        app.add_system(reload_level);
     }
 }
+
+// The level file being watched and its last seen modification time.
+#[derive(Default)]
+struct LevelWatch {
+    path: String,
+    modified: Option<SystemTime>,
+    last_check: f32,
+}
+
+// Rebuilds the world when the file of the current level changes. The player stays
+// where they are if the cell can still be walked on, otherwise goes to the start.
+// Everything on the level is back, so the player is as they entered it, lest the
+// same items be collected twice.
+fn reload_level(
+    mut commands: Commands,
+    mut watch: Local<LevelWatch>,
+    campaign: Res<Campaign>,
+    game: Res<Game>,
+    level_entities: Query<Entity, With<LevelEntity>>,
+    time: Res<Time>,
+) {
+    let now = time.seconds_since_startup() as f32;
+    if watch.last_check + LEVEL_CHECK_INTERVAL > now {
+        return;
+    }
+    watch.last_check = now;
+
+    let path = campaign.current_level();
+    let modified = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok();
+    if watch.path != path {
+        // A level loaded by the campaign, nothing to reload.
+        watch.path = path.to_string();
+        watch.modified = modified;
+        return;
+    }
+    if watch.modified == modified {
+        return;
+    }
+    watch.modified = modified;
+
+    let level = match LevelGrid::read(path) {
+        Ok(level) => level,
+        Err(error) => {
+            warn!("Not reloading {}: {}", path, error);
+            return;
+        }
+    };
+    info!("Reloading {}", path);
+
+    let mut reloaded = Game::new(&level, game.entered_player().clone(), *game.rules());
+    reloaded.place_player(game.position());
+    replace_level(&mut commands, &level_entities, level, reloaded);
+}
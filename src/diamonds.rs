@@ -1,11 +1,13 @@
-use bevy::prelude::*;
-use std::fs::File;
-use std::io::BufRead;
-use std::io::BufReader;
-
+use crate::game::GameObject;
+use crate::game::GridPos;
+use crate::game::LevelEntity;
+use crate::level::LevelGrid;
+use crate::level::TileKind;
+use crate::sim::Game;
+use crate::sim::GameEvent;
 use crate::textures::spawn_from_textures;
 use crate::textures::CharacterTextures;
-use crate::TILE_SIZE;
+use bevy::prelude::*;
 
 pub const DIAMOND_IDX: usize = 8;
 
@@ -16,37 +18,78 @@ pub struct DiamondDetect;
 
 impl Plugin for DiamondsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(spawn_diamonds);
+        app.add_system(spawn_diamonds)
+            .add_system(spawn_dropped_diamonds.after("step_game"));
     }
 }
 
-// Creates diamonds vector and places diamonds on the map, basing on /assets/map.txt file
-fn spawn_diamonds(mut commands: Commands, texture: Res<CharacterTextures>) {
-    let file = File::open("assets/map.txt").expect("Couldn't open map asset!");
+// Creates diamonds vector and places diamonds on the map, basing on the loaded level
+fn spawn_diamonds(
+    mut commands: Commands,
+    texture: Res<CharacterTextures>,
+    level: Res<LevelGrid>,
+    game: Res<Game>,
+) {
+    if !level.is_changed() {
+        return;
+    }
+
     let mut diamonds_storage = Vec::new();
 
-    for (y, line) in BufReader::new(file).lines().enumerate() {
-        if let Ok(line) = line {
-            for (x, char) in line.chars().enumerate() {
-                if char == 'p' {
-                    let diamond = spawn_from_textures(
-                        &mut commands,
-                        &texture,
-                        DIAMOND_IDX,
-                        Vec3::new(x as f32 * TILE_SIZE, -(y as f32) * TILE_SIZE, 150.0),
-                    );
-
-                    commands.entity(diamond).insert(DiamondDetect);
-                    diamonds_storage.push(diamond);
-                }
-            }
+    for (id, object) in game.objects() {
+        if object.kind == TileKind::Diamond {
+            let diamond = spawn_from_textures(
+                &mut commands,
+                &texture,
+                DIAMOND_IDX,
+                GridPos::from(object.cell),
+                150.0,
+            );
+
+            commands
+                .entity(diamond)
+                .insert(DiamondDetect)
+                .insert(GameObject(id));
+            diamonds_storage.push(diamond);
         }
     }
 
     commands
         .spawn()
         .insert(Name::new("Diamonds"))
+        .insert(LevelEntity)
         .insert(Transform::default())
         .insert(GlobalTransform::default())
         .push_children(&diamonds_storage);
 }
+
+// Diamonds left by crushed enemies appear during the game.
+fn spawn_dropped_diamonds(
+    mut commands: Commands,
+    texture: Res<CharacterTextures>,
+    mut events: EventReader<GameEvent>,
+    game: Res<Game>,
+) {
+    for event in events.iter() {
+        let (id, object) = match event {
+            GameEvent::ObjectAdded(id) => match game.object(*id) {
+                Some(object) if object.kind == TileKind::Diamond => (*id, object),
+                _ => continue,
+            },
+            _ => continue,
+        };
+
+        let diamond = spawn_from_textures(
+            &mut commands,
+            &texture,
+            DIAMOND_IDX,
+            GridPos::from(object.cell),
+            150.0,
+        );
+        commands
+            .entity(diamond)
+            .insert(DiamondDetect)
+            .insert(GameObject(id))
+            .insert(LevelEntity);
+    }
+}
@@ -0,0 +1,116 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use crate::rules::Effect;
+use crate::sim::GameEvent;
+
+// Gameplay events, sent after each tick for whatever wants to react to them
+// (sounds, statistics, the HUD...). Cells are map cells, as in GridPos.
+
+pub struct EventsPlugin;
+
+pub struct DiamondCollected {
+    pub cell: IVec2,
+}
+
+pub struct KeyCollected {
+    pub cell: IVec2,
+}
+
+pub struct DoorOpened {
+    pub cell: IVec2,
+}
+
+pub struct LifeCollected {
+    pub cell: IVec2,
+}
+
+pub struct BushCleared {
+    pub cell: IVec2,
+}
+
+pub struct WallBroken {
+    pub cell: IVec2,
+}
+
+// An enemy (or whatever the tile table makes crushable) under a falling stone.
+pub struct ObjectCrushed {
+    pub cell: IVec2,
+}
+
+pub struct PlayerDamaged {
+    pub lives_left: usize,
+}
+
+pub struct PlayerDied;
+
+pub struct GameSaved {
+    pub cell: IVec2,
+}
+
+#[derive(SystemParam)]
+struct GameplayWriters<'w, 's> {
+    diamonds: EventWriter<'w, 's, DiamondCollected>,
+    keys: EventWriter<'w, 's, KeyCollected>,
+    doors: EventWriter<'w, 's, DoorOpened>,
+    lives: EventWriter<'w, 's, LifeCollected>,
+    bushes: EventWriter<'w, 's, BushCleared>,
+    walls: EventWriter<'w, 's, WallBroken>,
+    crushed: EventWriter<'w, 's, ObjectCrushed>,
+    damage: EventWriter<'w, 's, PlayerDamaged>,
+    deaths: EventWriter<'w, 's, PlayerDied>,
+    saves: EventWriter<'w, 's, GameSaved>,
+}
+
+impl Plugin for EventsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DiamondCollected>()
+            .add_event::<KeyCollected>()
+            .add_event::<DoorOpened>()
+            .add_event::<LifeCollected>()
+            .add_event::<BushCleared>()
+            .add_event::<WallBroken>()
+            .add_event::<ObjectCrushed>()
+            .add_event::<PlayerDamaged>()
+            .add_event::<PlayerDied>()
+            .add_event::<GameSaved>()
+            .add_system(
+                send_gameplay_events
+                    .label("gameplay_events")
+                    .after("step_game"),
+            );
+    }
+}
+
+// Turns the events of the game rules into the gameplay events above.
+fn send_gameplay_events(mut events: EventReader<GameEvent>, mut writers: GameplayWriters) {
+    for event in events.iter() {
+        match *event {
+            GameEvent::Interacted(interactable, (x, y)) => {
+                let cell = IVec2::new(x, y);
+                match interactable.effect {
+                    Effect::AddDiamond => writers.diamonds.send(DiamondCollected { cell }),
+                    Effect::AddKey => writers.keys.send(KeyCollected { cell }),
+                    Effect::UseKey => writers.doors.send(DoorOpened { cell }),
+                    Effect::AddLife => writers.lives.send(LifeCollected { cell }),
+                    Effect::Save => writers.saves.send(GameSaved { cell }),
+                    Effect::None if interactable.destroy_on_enter => {
+                        writers.bushes.send(BushCleared { cell })
+                    }
+                    Effect::None | Effect::ReachExit => {}
+                }
+            }
+            GameEvent::WallBroken((x, y)) => writers.walls.send(WallBroken {
+                cell: IVec2::new(x, y),
+            }),
+            GameEvent::ObjectCrushed(_, (x, y)) => writers.crushed.send(ObjectCrushed {
+                cell: IVec2::new(x, y),
+            }),
+            GameEvent::PlayerDamaged(lives_left) => {
+                writers.damage.send(PlayerDamaged { lives_left })
+            }
+            GameEvent::PlayerDied => writers.deaths.send(PlayerDied),
+            _ => {}
+        }
+    }
+}
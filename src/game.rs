@@ -0,0 +1,178 @@
+use bevy::core::FixedTimestep;
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use std::collections::HashMap;
+
+use crate::level::LevelGrid;
+use crate::player::read_controls;
+use crate::sim::Cell;
+use crate::sim::Controls;
+use crate::sim::Game;
+use crate::sim::GameEvent;
+use crate::sim::ObjectId;
+use crate::sim::TICK_SECONDS;
+use crate::TILE_SIZE;
+
+pub struct GamePlugin;
+
+// GamePlugin playing one tick on each update of the app rather than on a timer,
+// for headless runs which must not depend on the clock.
+pub struct SteppedGamePlugin;
+
+// Marks top-level entities spawned from the level (with their children).
+#[derive(Component)]
+pub struct LevelEntity;
+
+// Sprite of an object of the simulation.
+#[derive(Component)]
+pub struct GameObject(pub ObjectId);
+
+// Map cell of a sprite, x growing to the right and y downwards. Sprites are moved
+// by changing it; sync_transforms writes their Transforms.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct GridPos(pub IVec2);
+
+impl GridPos {
+    // World position of the cell, at the given depth.
+    pub fn translation(&self, z: f32) -> Vec3 {
+        Vec3::new(
+            self.0.x as f32 * TILE_SIZE,
+            -(self.0.y as f32) * TILE_SIZE,
+            z,
+        )
+    }
+}
+
+impl From<Cell> for GridPos {
+    fn from(cell: Cell) -> GridPos {
+        GridPos(IVec2::new(cell.0, cell.1))
+    }
+}
+
+// Sprite of each object of the game, filled as the sprites are spawned.
+#[derive(Default)]
+pub struct ObjectSprites(HashMap<ObjectId, Entity>);
+
+// Keys pressed since the last tick, so that a press shorter than a tick still counts.
+#[derive(Default)]
+struct PendingControls(Controls);
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        add_game_systems(
+            app,
+            SystemSet::new().with_run_criteria(FixedTimestep::step(TICK_SECONDS)),
+        );
+    }
+}
+
+impl Plugin for SteppedGamePlugin {
+    fn build(&self, app: &mut App) {
+        add_game_systems(app, SystemSet::new());
+    }
+}
+
+// The systems of both plugins, the ticks being played in the given set.
+fn add_game_systems(app: &mut App, ticks: SystemSet) {
+    app.add_event::<GameEvent>()
+        .init_resource::<ObjectSprites>()
+        .init_resource::<PendingControls>()
+        .add_system(collect_controls.label("collect_controls"))
+        .add_system_set(ticks.with_system(step_game.label("step_game").after("collect_controls")))
+        .add_system(index_sprites.label("index_sprites"))
+        .add_system(update_objects.after("step_game").after("index_sprites"))
+        .add_system_to_stage(
+            CoreStage::PostUpdate,
+            sync_transforms.before(TransformSystem::TransformPropagate),
+        );
+}
+
+fn collect_controls(keyboard: Res<Input<KeyCode>>, mut pending: ResMut<PendingControls>) {
+    pending.0 = pending.0.merge(&read_controls(&keyboard));
+}
+
+// Plays a tick of the game and passes on what happened. Runs at a fixed rate,
+// as many times in a frame as the ticks due.
+fn step_game(
+    mut game: ResMut<Game>,
+    mut pending: ResMut<PendingControls>,
+    mut events: EventWriter<GameEvent>,
+) {
+    let events_happened = game.step(&pending.0);
+    pending.0 = Controls::default();
+    events.send_batch(events_happened.into_iter());
+}
+
+// Keeps ObjectSprites up to date. Sprites of a replaced level are gone with it.
+// New sprites are put where their object is now, or despawned if it is gone, as
+// the events of the ticks played before they were indexed did not reach them.
+fn index_sprites(
+    mut commands: Commands,
+    mut sprites: ResMut<ObjectSprites>,
+    level: Res<LevelGrid>,
+    game: Res<Game>,
+    mut sprite_query: Query<(Entity, &GameObject, &mut GridPos), Added<GameObject>>,
+) {
+    if level.is_changed() {
+        sprites.0.clear();
+    }
+
+    for (entity, object, mut grid_pos) in sprite_query.iter_mut() {
+        match game.object(object.0) {
+            Some(current) => {
+                *grid_pos = GridPos::from(current.cell);
+                sprites.0.insert(object.0, entity);
+            }
+            None => commands.entity(entity).despawn(),
+        }
+    }
+}
+
+// Moves and despawns object sprites after the objects of the game.
+fn update_objects(
+    mut commands: Commands,
+    mut events: EventReader<GameEvent>,
+    mut sprites: ResMut<ObjectSprites>,
+    mut grid_query: Query<&mut GridPos, With<GameObject>>,
+) {
+    for event in events.iter() {
+        match event {
+            GameEvent::ObjectMoved(id, cell) => {
+                let sprite = sprites.0.get(id).copied();
+                if let Some(mut grid_pos) =
+                    sprite.and_then(|entity| grid_query.get_mut(entity).ok())
+                {
+                    *grid_pos = GridPos::from(*cell);
+                }
+            }
+            GameEvent::ObjectRemoved(id) => {
+                if let Some(entity) = sprites.0.remove(id) {
+                    commands.entity(entity).despawn();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn sync_transforms(mut sprite_query: Query<(&GridPos, &mut Transform), Changed<GridPos>>) {
+    for (grid_pos, mut transform) in sprite_query.iter_mut() {
+        transform.translation = grid_pos.translation(transform.translation.z);
+    }
+}
+
+// Despawns everything spawned from the current level and puts the given one, with the
+// game played on it, in its place. Spawners run again when they see the LevelGrid
+// resource changed.
+pub fn replace_level(
+    commands: &mut Commands,
+    level_entities: &Query<Entity, With<LevelEntity>>,
+    level: LevelGrid,
+    game: Game,
+) {
+    for entity in level_entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.insert_resource(level);
+    commands.insert_resource(game);
+}
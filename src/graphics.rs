@@ -24,18 +24,9 @@ pub enum AnimationDirection {
     HammerRight,
 }
 
-pub enum FacingDirection {
-    Left,
-    Right,
-    Up,
-    Down,
-}
-
 #[derive(Component)]
 pub struct PlayerGraphics {
     pub animation: AnimationDirection,
-    pub facing: FacingDirection,
-    pub hammer_done: i32,
 }
 
 #[derive(Component)]
@@ -43,7 +34,6 @@ pub struct FrameAnimation {
     pub frame_timer: Timer,
     pub frames: Vec<usize>,
     pub current_frame: usize,
-    pub instant_frame: bool,
 }
 
 impl Plugin for GraphicsPlugin {
@@ -95,35 +85,14 @@ impl GraphicsPlugin {
     }
 
     fn frame_animation(
-        mut npc_query: Query<(
-            &mut TextureAtlasSprite,
-            &mut FrameAnimation,
-            &mut PlayerGraphics,
-        )>,
+        mut npc_query: Query<(&mut TextureAtlasSprite, &mut FrameAnimation)>,
         time: Res<Time>,
     ) {
-        for (mut texture, mut frame, mut player) in npc_query.iter_mut() {
+        for (mut texture, mut frame) in npc_query.iter_mut() {
             frame.frame_timer.tick(time.delta());
-            if (player.animation == AnimationDirection::HammerRight
-                || player.animation == AnimationDirection::HammerLeft)
-                && player.hammer_done < 3
-            {
-                player.hammer_done += 1
-            } else if frame.frame_timer.just_finished() {
+            if frame.frame_timer.just_finished() {
                 frame.current_frame = (frame.current_frame + 1) % frame.frames.len();
                 texture.index = frame.frames[frame.current_frame];
-            } else if (player.animation == AnimationDirection::HammerRight
-                || player.animation == AnimationDirection::HammerLeft)
-                && player.hammer_done == 3
-            {
-                player.hammer_done = 0;
-                let animation_left;
-                (player, animation_left) = is_animation_left(player);
-                if animation_left {
-                    player.animation = AnimationDirection::Left;
-                } else {
-                    player.animation = AnimationDirection::Right;
-                }
             }
         }
     }
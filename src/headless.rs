@@ -0,0 +1,31 @@
+use bevy::prelude::*;
+
+use crate::graphics::CharacterSheet;
+use crate::textures::CharacterTextures;
+
+// Stands in for the window, the keyboard and the textures when the game runs
+// without a window: sprites are spawned with empty textures and no key is pressed.
+pub struct HeadlessPlugin;
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Input<KeyCode>>()
+            .insert_resource(CharacterTextures(Handle::default()))
+            .insert_resource(CharacterSheet {
+                handle: Handle::default(),
+                player_left: [0; 5],
+                player_right: [0; 5],
+                player_hit_right: [0; 2],
+                player_hit_left: [0; 2],
+                player_dead: [0; 1],
+                player_hammer_right: [0; 2],
+                player_hammer_left: [0; 2],
+            })
+            .add_startup_system(spawn_camera);
+    }
+}
+
+// The player is followed by a camera, even when nothing is drawn.
+fn spawn_camera(mut commands: Commands) {
+    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+}
@@ -0,0 +1,415 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+
+use crate::level_file::parse_layered;
+use crate::tiled::parse_tiled;
+
+pub const LEGEND_HEADER: &str = "[legend]";
+pub const MAP_HEADER: &str = "[map]";
+// Break between two steps of an enemy, in seconds, when the level does not say.
+pub const DEFAULT_ENEMY_SPEED: f32 = 0.2;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PatrolAxis {
+    Horizontal,
+    Vertical,
+}
+
+// Enemy placed on the map. Direction 1 is right (horizontal) or down (vertical),
+// speed is the break between two steps in seconds.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct EnemySpawn {
+    pub axis: PatrolAxis,
+    pub dir: i32,
+    pub speed: f32,
+}
+
+impl EnemySpawn {
+    fn new(axis: PatrolAxis, dir: i32) -> EnemySpawn {
+        EnemySpawn {
+            axis,
+            dir,
+            speed: DEFAULT_ENEMY_SPEED,
+        }
+    }
+}
+
+// Kind of a single map cell. `Empty` marks cells past the end of a shorter line.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TileKind {
+    Empty,
+    Grass,
+    Wall,
+    Britle,
+    Bush,
+    Key,
+    Door,
+    Diamond,
+    Life,
+    Stone,
+    SavePoint,
+    Start,
+    Exit,
+    Enemy(EnemySpawn),
+}
+
+impl TileKind {
+    // Name used for the kind in map legends.
+    pub fn from_name(name: &str) -> Option<TileKind> {
+        match name {
+            "grass" => Some(TileKind::Grass),
+            "wall" => Some(TileKind::Wall),
+            "britle" => Some(TileKind::Britle),
+            "bush" => Some(TileKind::Bush),
+            "key" => Some(TileKind::Key),
+            "door" => Some(TileKind::Door),
+            "diamond" => Some(TileKind::Diamond),
+            "life" => Some(TileKind::Life),
+            "stone" => Some(TileKind::Stone),
+            "save_point" => Some(TileKind::SavePoint),
+            "start" => Some(TileKind::Start),
+            "exit" => Some(TileKind::Exit),
+            "enemy_right" => Some(TileKind::Enemy(EnemySpawn::new(PatrolAxis::Horizontal, 1))),
+            "enemy_left" => Some(TileKind::Enemy(EnemySpawn::new(PatrolAxis::Horizontal, -1))),
+            "enemy_down" => Some(TileKind::Enemy(EnemySpawn::new(PatrolAxis::Vertical, 1))),
+            "enemy_up" => Some(TileKind::Enemy(EnemySpawn::new(PatrolAxis::Vertical, -1))),
+            _ => None,
+        }
+    }
+}
+
+// Layer of the level a kind of tile belongs to. A cell has exactly one terrain tile
+// and any number of objects and entities on top of it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Layer {
+    Terrain,
+    Objects,
+    Entities,
+}
+
+impl TileKind {
+    pub fn layer(&self) -> Layer {
+        match self {
+            TileKind::Empty | TileKind::Grass | TileKind::Wall | TileKind::Britle => Layer::Terrain,
+            TileKind::Start | TileKind::Enemy(_) => Layer::Entities,
+            _ => Layer::Objects,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LevelError {
+    BadLegendEntry { line: usize },
+    UnknownKind { line: usize, name: String },
+    MissingStart,
+    DuplicateStart { x: usize, y: usize },
+    BadLevelFile(String),
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LevelError::BadLegendEntry { line } => {
+                write!(f, "line {}: expected `<symbol> = <kind> [speed]`", line)
+            }
+            LevelError::UnknownKind { line, name } => {
+                write!(f, "line {}: unknown tile kind `{}`", line, name)
+            }
+            LevelError::MissingStart => write!(f, "the map has no start tile"),
+            LevelError::DuplicateStart { x, y } => write!(
+                f,
+                "second start tile at column {}, row {} of the map",
+                x + 1,
+                y + 1
+            ),
+            LevelError::BadLevelFile(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+// Mapping of map symbols to tile kinds.
+pub struct Legend {
+    symbols: HashMap<char, TileKind>,
+}
+
+impl Default for Legend {
+    fn default() -> Legend {
+        Legend {
+            symbols: HashMap::from([
+                ('x', TileKind::Wall),
+                ('o', TileKind::Grass),
+                ('0', TileKind::Britle),
+                ('b', TileKind::Bush),
+                ('k', TileKind::Key),
+                ('d', TileKind::Door),
+                ('p', TileKind::Diamond),
+                ('+', TileKind::Life),
+                ('@', TileKind::Stone),
+                ('*', TileKind::SavePoint),
+                ('s', TileKind::Start),
+                ('g', TileKind::Exit),
+                (
+                    '>',
+                    TileKind::Enemy(EnemySpawn::new(PatrolAxis::Horizontal, 1)),
+                ),
+                (
+                    '<',
+                    TileKind::Enemy(EnemySpawn::new(PatrolAxis::Horizontal, -1)),
+                ),
+                (
+                    'v',
+                    TileKind::Enemy(EnemySpawn::new(PatrolAxis::Vertical, 1)),
+                ),
+                (
+                    '^',
+                    TileKind::Enemy(EnemySpawn::new(PatrolAxis::Vertical, -1)),
+                ),
+            ]),
+        }
+    }
+}
+
+impl Legend {
+    pub fn contains(&self, symbol: char) -> bool {
+        self.symbols.contains_key(&symbol)
+    }
+
+    // Unknown symbols are treated as grass.
+    pub fn kind(&self, symbol: char) -> TileKind {
+        *self.symbols.get(&symbol).unwrap_or(&TileKind::Grass)
+    }
+
+    // Adds (or overrides) one `<symbol> = <kind> [speed]` entry. Speed is only allowed for enemies.
+    fn add_entry(&mut self, entry: &str, line: usize) -> Result<(), LevelError> {
+        let (symbol, name) = entry
+            .split_once('=')
+            .ok_or(LevelError::BadLegendEntry { line })?;
+
+        let mut symbol_chars = symbol.trim().chars();
+        let symbol = match (symbol_chars.next(), symbol_chars.next()) {
+            (Some(symbol), None) => symbol,
+            _ => return Err(LevelError::BadLegendEntry { line }),
+        };
+
+        let mut words = name.split_whitespace();
+        let name = words.next().ok_or(LevelError::BadLegendEntry { line })?;
+        let mut kind = TileKind::from_name(name).ok_or_else(|| LevelError::UnknownKind {
+            line,
+            name: name.to_string(),
+        })?;
+
+        if let Some(speed) = words.next() {
+            let speed = speed
+                .parse::<f32>()
+                .map_err(|_| LevelError::BadLegendEntry { line })?;
+            match &mut kind {
+                TileKind::Enemy(enemy) if speed > 0.0 => enemy.speed = speed,
+                _ => return Err(LevelError::BadLegendEntry { line }),
+            }
+        }
+        if words.next().is_some() {
+            return Err(LevelError::BadLegendEntry { line });
+        }
+
+        self.symbols.insert(symbol, kind);
+        Ok(())
+    }
+}
+
+// Splits a map in the text format into its legend (default symbols with the ones
+// from an optional `[legend]` section ended with `[map]` added on top), the map rows
+// and the line number of the first row.
+pub fn split_legend(source: &str) -> Result<(Legend, Vec<&str>, usize), LevelError> {
+    let mut legend = Legend::default();
+    let mut map_lines = source.lines().collect::<Vec<_>>();
+    let mut first_line = 1;
+
+    if map_lines.first().map(|line| line.trim()) == Some(LEGEND_HEADER) {
+        let map_start = map_lines
+            .iter()
+            .position(|line| line.trim() == MAP_HEADER)
+            .unwrap_or(map_lines.len());
+
+        for (idx, entry) in map_lines[1..map_start].iter().enumerate() {
+            if !entry.trim().is_empty() {
+                legend.add_entry(entry, idx + 2)?;
+            }
+        }
+
+        first_line = map_start + 2;
+        map_lines.drain(..(map_start + 1).min(map_lines.len()));
+    }
+
+    Ok((legend, map_lines, first_line))
+}
+
+// Level metadata. Maps in the text format only get the name (their path).
+#[derive(Default)]
+pub struct LevelInfo {
+    pub name: String,
+    pub author: String,
+    pub required_diamonds: usize,
+    pub par_time: Option<f32>,
+}
+
+// Parsed level, shared by all the spawners.
+pub struct LevelGrid {
+    width: usize,
+    height: usize,
+    terrain: Vec<TileKind>,
+    objects: Vec<(usize, usize, TileKind)>,
+    entities: Vec<(usize, usize, TileKind)>,
+    pub start: (usize, usize),
+    pub info: LevelInfo,
+    // Line of the file holding the first row, for levels in the text format.
+    pub first_line: Option<usize>,
+}
+
+impl LevelGrid {
+    // Empty level of the given size, to be filled by the loaders.
+    pub fn new(width: usize, height: usize) -> LevelGrid {
+        LevelGrid {
+            width,
+            height,
+            terrain: vec![TileKind::Empty; width * height],
+            objects: Vec::new(),
+            entities: Vec::new(),
+            start: (0, 0),
+            info: LevelInfo::default(),
+            first_line: None,
+        }
+    }
+
+    // Builds a level from rows of symbols. Symbols of objects and entities are put
+    // on their layers, with grass under them.
+    pub fn from_rows<'a>(
+        rows: impl Iterator<Item = &'a str>,
+        legend: &Legend,
+    ) -> Result<LevelGrid, LevelError> {
+        let rows: Vec<Vec<TileKind>> = rows
+            .map(|line| line.chars().map(|symbol| legend.kind(symbol)).collect())
+            .collect();
+
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        let mut level = LevelGrid::new(width, rows.len());
+
+        for (y, row) in rows.into_iter().enumerate() {
+            for (x, tile) in row.into_iter().enumerate() {
+                if tile.layer() == Layer::Terrain {
+                    level.terrain[y * width + x] = tile;
+                } else {
+                    level.terrain[y * width + x] = TileKind::Grass;
+                    level.place(x, y, tile)?;
+                }
+            }
+        }
+
+        Ok(level)
+    }
+
+    pub fn set_terrain(&mut self, x: usize, y: usize, tile: TileKind) {
+        assert!(tile.layer() == Layer::Terrain);
+        self.terrain[y * self.width + x] = tile;
+    }
+
+    // Parses a map in the text format.
+    pub fn parse(source: &str) -> Result<LevelGrid, LevelError> {
+        let (legend, map_lines, first_line) = split_legend(source)?;
+        let mut level = LevelGrid::from_rows(map_lines.into_iter(), &legend)?;
+        level.first_line = Some(first_line);
+        level.find_start()?;
+        Ok(level)
+    }
+
+    // Reads a level: `.json` files in the layered format, `.tmj` files exported
+    // from Tiled and the text format otherwise.
+    pub fn read(path: &str) -> Result<LevelGrid, LevelError> {
+        let source = fs::read_to_string(path)
+            .map_err(|error| LevelError::BadLevelFile(error.to_string()))?;
+        let mut level = if path.ends_with(".json") {
+            parse_layered(&source)
+        } else if path.ends_with(".tmj") {
+            parse_tiled(&source)
+        } else {
+            LevelGrid::parse(&source)
+        }?;
+
+        if level.info.name.is_empty() {
+            level.info.name = path.to_string();
+        }
+        Ok(level)
+    }
+
+    // Same as read, but a broken level file stops the game.
+    pub fn load(path: &str) -> LevelGrid {
+        LevelGrid::read(path).unwrap_or_else(|error| panic!("{}: {}", path, error))
+    }
+
+    // Puts an object or an entity on the map.
+    pub fn place(&mut self, x: usize, y: usize, tile: TileKind) -> Result<(), LevelError> {
+        if x >= self.width || y >= self.height {
+            return Err(LevelError::BadLevelFile(format!(
+                "{:?} at ({}, {}) is outside the map",
+                tile, x, y
+            )));
+        }
+
+        match tile.layer() {
+            Layer::Terrain => {
+                return Err(LevelError::BadLevelFile(format!(
+                    "{:?} at ({}, {}) is a terrain tile",
+                    tile, x, y
+                )))
+            }
+            Layer::Objects => self.objects.push((x, y, tile)),
+            Layer::Entities => self.entities.push((x, y, tile)),
+        }
+        Ok(())
+    }
+
+    // Sets the start to the only start tile of the level.
+    pub fn find_start(&mut self) -> Result<(), LevelError> {
+        let mut starts = self
+            .entities
+            .iter()
+            .filter(|(_, _, tile)| *tile == TileKind::Start);
+
+        let (x, y, _) = starts.next().ok_or(LevelError::MissingStart)?;
+        self.start = (*x, *y);
+        if let Some((x, y, _)) = starts.next() {
+            return Err(LevelError::DuplicateStart { x: *x, y: *y });
+        }
+        Ok(())
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn terrain_at(&self, x: usize, y: usize) -> TileKind {
+        self.terrain[y * self.width + x]
+    }
+
+    // Iterates over (x, y, kind) of the terrain, row by row.
+    pub fn terrain(&self) -> impl Iterator<Item = (usize, usize, TileKind)> + '_ {
+        self.terrain
+            .iter()
+            .enumerate()
+            .map(|(idx, tile)| (idx % self.width, idx / self.width, *tile))
+    }
+
+    // Iterates over (x, y, kind) of everything on the level: terrain, objects and entities.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize, TileKind)> + '_ {
+        self.terrain()
+            .chain(self.objects.iter().copied())
+            .chain(self.entities.iter().copied())
+    }
+}
+
+// Despawns everything spawned from the current level and puts the given one, with the
+// game played on it, in its plac
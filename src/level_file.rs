@@ -0,0 +1,96 @@
+use serde::Deserialize;
+
+use crate::level::Layer;
+use crate::level::Legend;
+use crate::level::LevelError;
+use crate::level::LevelGrid;
+use crate::level::LevelInfo;
+use crate::level::TileKind;
+
+// Layered level format. Terrain rows use the default map symbols (objects and
+// entities written there are placed as in the text format), the other layers
+// list what stands on the terrain, any number of things per cell.
+#[derive(Deserialize)]
+struct LevelFile {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    author: String,
+    #[serde(default)]
+    required_diamonds: usize,
+    par_time: Option<f32>,
+    terrain: Vec<String>,
+    #[serde(default)]
+    objects: Vec<PlacedTile>,
+    #[serde(default)]
+    entities: Vec<PlacedTile>,
+}
+
+#[derive(Deserialize)]
+struct PlacedTile {
+    kind: String,
+    x: usize,
+    y: usize,
+    speed: Option<f32>,
+}
+
+impl PlacedTile {
+    fn tile_kind(&self, layer: Layer) -> Result<TileKind, LevelError> {
+        let mut kind = TileKind::from_name(&self.kind).ok_or_else(|| {
+            LevelError::BadLevelFile(format!("unknown tile kind `{}`", self.kind))
+        })?;
+
+        if kind.layer() != layer {
+            return Err(LevelError::BadLevelFile(format!(
+                "`{}` at ({}, {}) does not belong to the {:?} layer",
+                self.kind, self.x, self.y, layer
+            )));
+        }
+
+        if let Some(speed) = self.speed {
+            match &mut kind {
+                TileKind::Enemy(enemy) if speed > 0.0 => enemy.speed = speed,
+                _ => {
+                    return Err(LevelError::BadLevelFile(format!(
+                        "`{}` at ({}, {}) cannot have speed {}",
+                        self.kind, self.x, self.y, speed
+                    )))
+                }
+            }
+        }
+
+        Ok(kind)
+    }
+}
+
+// Parses a level in the layered (JSON) format.
+pub fn parse_layered(source: &str) -> Result<LevelGrid, LevelError> {
+    let file: LevelFile = serde_json::from_str(source)
+        .map_err(|error| LevelError::BadLevelFile(error.to_string()))?;
+
+    let legend = Legend::default();
+    let mut level = LevelGrid::from_rows(file.terrain.iter().map(|row| row.as_str()), &legend)?;
+
+    for placed in file.objects.iter() {
+        level.place(placed.x, placed.y, placed.tile_kind(Layer::Objects)?)?;
+    }
+    for placed in file.entities.iter() {
+        level.place(placed.x, placed.y, placed.tile_kind(Layer::Entities)?)?;
+    }
+
+    level.info = LevelInfo {
+        name: file.name,
+        author: file.author,
+        required_diamonds: file.required_diamonds,
+        par_time: file.par_time,
+    };
+    level.find_start()?;
+    Ok(level)
+}
+
+// Terrain rows of a level in the layered format, as written in the file.
+pub fn layered_terrain(source: &str) -> Result<Vec<String>, LevelError> {
+    let file: LevelFile = serde_json::from_str(source)
+        .map_err(|error| LevelError::BadLevelFile(error.to_string()))?;
+    Ok(file.terrain)
+}
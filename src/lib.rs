@@ -0,0 +1,54 @@
+pub const RESOLUTION: f32 = 16.0 / 9.0;
+pub const TILE_SIZE: f32 = 0.3;
+pub const WINDOW_HEIGHT: f32 = 900.0;
+
+pub mod bushes;
+pub mod campaign;
+pub mod debug;
+pub mod diamonds;
+pub mod doors;
+pub mod enemy;
+pub mod events;
+pub mod game;
+pub mod graphics;
+pub mod headless;
+pub mod keys;
+pub mod level;
+pub mod level_file;
+pub mod lives;
+pub mod options;
+pub mod player;
+pub mod random;
+pub mod rules;
+pub mod save_point;
+pub mod sim;
+pub mod solver;
+pub mod stones;
+pub mod textures;
+pub mod tiled;
+pub mod validate;
+pub mod worldmap;
+
+use bevy::app::PluginGroupBuilder;
+use bevy::prelude::*;
+
+// Plugins spawning and updating the sprites of the game, with or without a window.
+// The game itself (GamePlugin or SteppedGamePlugin) and the textures are added apart.
+pub struct GameplayPlugins;
+
+impl PluginGroup for GameplayPlugins {
+    fn build(&mut self, group: &mut PluginGroupBuilder) {
+        group
+            .add(events::EventsPlugin)
+            .add(player::PlayerPlugin)
+            .add(worldmap::WorldMapPlugin)
+            .add(bushes::BushesPlugin)
+            .add(keys::KeysPlugin)
+            .add(doors::DoorsPlugin)
+            .add(diamonds::DiamondsPlugin)
+            .add(save_point::SavePointPlugin)
+            .add(enemy::EnemyPlugin)
+            .add(lives::LivesPlugin)
+            .add(stones::StonesPlugin);
+    }
+}
@@ -1,11 +1,12 @@
-use bevy::prelude::*;
-use std::fs::File;
-use std::io::BufRead;
-use std::io::BufReader;
-
+use crate::game::GameObject;
+use crate::game::GridPos;
+use crate::game::LevelEntity;
+use crate::level::LevelGrid;
+use crate::level::TileKind;
+use crate::sim::Game;
 use crate::textures::spawn_from_textures;
 use crate::textures::CharacterTextures;
-use crate::TILE_SIZE;
+use bevy::prelude::*;
 
 pub const LIFE_IDX: usize = 5;
 
@@ -16,36 +17,45 @@ pub struct LifeDetect;
 
 impl Plugin for LivesPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(spawn_lives);
+        app.add_system(spawn_lives);
     }
 }
 
-// Creates lives vector and places lives on the map, basing on /assets/map.txt file
-fn spawn_lives(mut commands: Commands, texture: Res<CharacterTextures>) {
-    let file = File::open("assets/map.txt").expect("Couldn't open map asset!");
+// Creates lives vector and places lives on the map, basing on the loaded level
+fn spawn_lives(
+    mut commands: Commands,
+    texture: Res<CharacterTextures>,
+    level: Res<LevelGrid>,
+    game: Res<Game>,
+) {
+    if !level.is_changed() {
+        return;
+    }
+
     let mut lives_storage = Vec::new();
 
-    for (y, line) in BufReader::new(file).lines().enumerate() {
-        if let Ok(line) = line {
-            for (x, char) in line.chars().enumerate() {
-                if char == '+' {
-                    let life = spawn_from_textures(
-                        &mut commands,
-                        &texture,
-                        LIFE_IDX,
-                        Vec3::new(x as f32 * TILE_SIZE, -(y as f32) * TILE_SIZE, 150.0),
-                    );
-
-                    commands.entity(life).insert(LifeDetect);
-                    lives_storage.push(life);
-                }
-            }
+    for (id, object) in game.objects() {
+        if object.kind == TileKind::Life {
+            let life = spawn_from_textures(
+                &mut commands,
+                &texture,
+                LIFE_IDX,
+                GridPos::from(object.cell),
+                150.0,
+            );
+
+            commands
+                .entity(life)
+                .insert(LifeDetect)
+                .insert(GameObject(id));
+            lives_storage.push(life);
         }
     }
 
     commands
         .spawn()
         .insert(Name::new("Lives"))
+        .insert(LevelEntity)
         .insert(Transform::default())
         .insert(GlobalTransform::default())
         .push_children(&lives_storage);
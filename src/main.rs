@@ -1,86 +1,120 @@
+use bevy::app::AppExit;
+use bevy::app::ScheduleRunnerSettings;
 use bevy::prelude::*;
 use bevy::render::camera::ScalingMode;
-
-pub const RESOLUTION: f32 = 16.0 / 9.0;
-pub const TILE_SIZE: f32 = 0.3;
-pub const WINDOW_HEIGHT: f32 = 900.0;
-
-mod debug;
-use debug::DebugPlugin;
-
-mod player;
-use player::PlayerPlugin;
-
-mod worldmap;
-use worldmap::WorldMapPlugin;
-
-mod textures;
-use textures::TexturesPlugin;
-
-mod bushes;
-use bushes::BushesPlugin;
-
-mod keys;
-use keys::KeysPlugin;
-
-mod doors;
-use doors::DoorsPlugin;
-
-mod diamonds;
-use diamonds::DiamondsPlugin;
-
-mod save_point;
-use save_point::SavePointPlugin;
-
-mod enemy;
-use enemy::EnemyPlugin;
-
-mod graphics;
-use graphics::GraphicsPlugin;
-
-mod lives;
-use lives::LivesPlugin;
-
-mod stones;
-use stones::StonesPlugin;
-
-fn main() {
-    App::new()
-        .insert_resource(WindowDescriptor {
-            width: WINDOW_HEIGHT * RESOLUTION,
-            height: WINDOW_HEIGHT,
+use bevy::window::WindowMode;
+use std::env;
+use std::fs;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use diamond_rust::campaign::Campaign;
+use diamond_rust::campaign::CampaignPlugin;
+use diamond_rust::debug::DebugPlugin;
+use diamond_rust::game::GamePlugin;
+use diamond_rust::game::SteppedGamePlugin;
+use diamond_rust::graphics::GraphicsPlugin;
+use diamond_rust::headless::HeadlessPlugin;
+use diamond_rust::options::Options;
+use diamond_rust::options::USAGE;
+use diamond_rust::player::SaveFile;
+use diamond_rust::player::SAVE_FILE_NAME;
+use diamond_rust::random::GameRng;
+use diamond_rust::sim::Game;
+use diamond_rust::sim::TICK_SECONDS;
+use diamond_rust::textures::TexturesPlugin;
+use diamond_rust::GameplayPlugins;
+
+// Number of ticks a headless run stops after.
+struct TickLimit(u64);
+
+fn main() -> ExitCode {
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    if options.help {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    let rng = match options.seed {
+        Some(seed) => GameRng::new(seed),
+        None => GameRng::from_entropy(),
+    };
+    println!("Seed: {}", rng.seed());
+
+    let campaign = match &options.level {
+        Some(level) => Campaign::new(vec![level.clone()]),
+        None => Campaign::load(&options.campaign),
+    };
+    fs::create_dir_all(&options.save_dir).expect("Couldn't create the save directory!");
+
+    let mut app = App::new();
+    app.insert_resource(rng)
+        .insert_resource(campaign)
+        .insert_resource(SaveFile(options.save_dir.join(SAVE_FILE_NAME)));
+
+    if options.headless {
+        app.insert_resource(ScheduleRunnerSettings::run_loop(Duration::from_secs_f64(
+            TICK_SECONDS,
+        )))
+        .add_plugins(MinimalPlugins)
+        .add_plugin(HeadlessPlugin)
+        .add_plugin(DebugPlugin { inspector: false })
+        .add_plugin(CampaignPlugin)
+        .add_plugin(SteppedGamePlugin)
+        .add_plugins(GameplayPlugins);
+
+        if let Some(ticks) = options.ticks {
+            app.insert_resource(TickLimit(ticks))
+                .add_system(stop_after_ticks.after("step_game"));
+        }
+    } else {
+        app.insert_resource(WindowDescriptor {
+            width: options.window_width,
+            height: options.window_height,
             title: "Diamond Rust".to_string(),
             vsync: true,
             resizable: false,
+            mode: if options.fullscreen {
+                WindowMode::BorderlessFullscreen
+            } else {
+                WindowMode::Windowed
+            },
             ..Default::default()
         })
         .add_plugins(DefaultPlugins)
         .add_startup_system(create_camera)
-        .add_plugin(DebugPlugin)
-        .add_plugin(PlayerPlugin)
-        .add_plugin(WorldMapPlugin)
-        .add_plugin(BushesPlugin)
-        .add_plugin(KeysPlugin)
-        .add_plugin(DoorsPlugin)
-        .add_plugin(DiamondsPlugin)
-        .add_plugin(SavePointPlugin)
-        .add_plugin(EnemyPlugin)
+        .add_plugin(DebugPlugin {
+            inspector: options.inspector,
+        })
+        .add_plugin(CampaignPlugin)
+        .add_plugin(GamePlugin)
+        .add_plugins(GameplayPlugins)
         .add_plugin(GraphicsPlugin)
-        .add_plugin(LivesPlugin)
-        .add_plugin(StonesPlugin)
-        .add_plugin(TexturesPlugin)
-        .run();
+        .add_plugin(TexturesPlugin);
+    }
+
+    app.run();
+    ExitCode::SUCCESS
 }
 
-// Camera spawner.
+// Camera spawner. The map is two units high, whatever the size of the window.
 fn create_camera(mut commands: Commands) {
     let mut camera = OrthographicCameraBundle::new_2d();
-
-    camera.orthographic_projection.right = 1.0 * RESOLUTION;
-    camera.orthographic_projection.left = -1.0 * RESOLUTION;
-    camera.orthographic_projection.top = 1.0;
-    camera.orthographic_projection.bottom = -1.0;
-    camera.orthographic_projection.scaling_mode = ScalingMode::None;
+    camera.orthographic_projection.scaling_mode = ScalingMode::FixedVertical;
 
     commands.spawn_bundle(camera);
 }
+
+// Ends a headless run after the given number of ticks and prints the player.
+fn stop_after_ticks(game: Res<Game>, limit: Res<TickLimit>, mut exit: EventWriter<AppExit>) {
+    if game.tick() >= limit.0 {
+        println!("{}", serde_json::to_string(game.player()).unwrap());
+        exit.send(AppExit);
+    }
+}
@@ -0,0 +1,98 @@
+use std::path::PathBuf;
+
+use crate::campaign::CAMPAIGN_PATH;
+use crate::RESOLUTION;
+use crate::WINDOW_HEIGHT;
+
+pub const USAGE: &str = "\
+Usage: diamond-rust [OPTIONS]
+
+Options:
+  --level <FILE>       play a single level instead of the campaign
+  --campaign <FILE>    play the levels listed in the file (default: assets/campaign.txt)
+  --save-dir <DIR>     write the save file to the directory (default: the current one)
+  --seed <NUMBER>      seed of the random decisions (default: a new one)
+  --window <W>x<H>     size of the window (default: 1600x900)
+  --fullscreen         fill the screen
+  --no-inspector       leave out the egui inspector of debug builds
+  --headless           run the game without a window, with no keys pressed
+  --ticks <NUMBER>     with --headless, stop after that many ticks and print the player
+  --help               print this message";
+
+// Options of the game binary.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Options {
+    // Single level to play, instead of the campaign.
+    pub level: Option<String>,
+    pub campaign: String,
+    pub save_dir: PathBuf,
+    pub seed: Option<u64>,
+    pub window_width: f32,
+    pub window_height: f32,
+    pub fullscreen: bool,
+    pub inspector: bool,
+    pub headless: bool,
+    pub ticks: Option<u64>,
+    pub help: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            level: None,
+            campaign: CAMPAIGN_PATH.to_string(),
+            save_dir: PathBuf::from("."),
+            seed: None,
+            window_width: WINDOW_HEIGHT * RESOLUTION,
+            window_height: WINDOW_HEIGHT,
+            fullscreen: false,
+            inspector: true,
+            headless: false,
+            ticks: None,
+            help: false,
+        }
+    }
+}
+
+impl Options {
+    // Parses the arguments following the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--level" => options.level = Some(value()?),
+                "--campaign" => options.campaign = value()?,
+                "--save-dir" => options.save_dir = PathBuf::from(value()?),
+                "--seed" => options.seed = Some(parse_number(&arg, &value()?)?),
+                "--window" => {
+                    let size = value()?;
+                    let (width, height) = size
+                        .split_once('x')
+                        .ok_or(format!("--window expects <W>x<H>, got `{}`", size))?;
+                    options.window_width = parse_number(&arg, width)?;
+                    options.window_height = parse_number(&arg, height)?;
+                }
+                "--fullscreen" => options.fullscreen = true,
+                "--no-inspector" => options.inspector = false,
+                "--headless" => options.headless = true,
+                "--ticks" => options.ticks = Some(parse_number(&arg, &value()?)?),
+                "--help" | "-h" => options.help = true,
+                _ => return Err(format!("unknown option `{}`", arg)),
+            }
+        }
+
+        if options.ticks.is_some() && !options.headless {
+            return Err("--ticks only works with --headless".to_string());
+        }
+        Ok(options)
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(option: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} expects a number, got `{}`", option, value))
+}
@@ -0,0 +1,222 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+
+use crate::level::TileKind;
+
+// Tile rules shared by the game systems and the level solver.
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    // Step on the map, where y grows downwards.
+    pub fn delta(&self) -> (i32, i32) {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+        }
+    }
+}
+
+pub const TILE_RULES_PATH: &str = "assets/tiles.json";
+
+// Names of the tile kinds in the behaviour table, as in map legends. Enemies share
+// one entry whatever their direction.
+const TILE_NAMES: [&str; 14] = [
+    "empty",
+    "grass",
+    "wall",
+    "britle",
+    "bush",
+    "key",
+    "door",
+    "diamond",
+    "life",
+    "stone",
+    "save_point",
+    "start",
+    "exit",
+    "enemy",
+];
+
+fn tile_slot(tile: TileKind) -> usize {
+    match tile {
+        TileKind::Empty => 0,
+        TileKind::Grass => 1,
+        TileKind::Wall => 2,
+        TileKind::Britle => 3,
+        TileKind::Bush => 4,
+        TileKind::Key => 5,
+        TileKind::Door => 6,
+        TileKind::Diamond => 7,
+        TileKind::Life => 8,
+        TileKind::Stone => 9,
+        TileKind::SavePoint => 10,
+        TileKind::Start => 11,
+        TileKind::Exit => 12,
+        TileKind::Enemy(_) => 13,
+    }
+}
+
+// What entering a cell holding an object does to the player.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Effect {
+    None,
+    AddKey,
+    UseKey,
+    AddDiamond,
+    AddLife,
+    Save,
+    ReachExit,
+}
+
+// An object the player walks into: its effect, and whether it is gone afterwards.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Interactable {
+    pub effect: Effect,
+    pub destroy_on_enter: bool,
+}
+
+// How a kind of tile behaves. Fields left out of the table take the values of grass.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TileBehavior {
+    pub walkable: bool,
+    pub enemy_passable: bool,
+    pub hammer_breakable: bool,
+    pub destroyed_on_enter: bool,
+    // Only walkable while holding a key, as doors are.
+    pub requires_key: bool,
+    // Lives taken from the player standing on the tile.
+    pub damage: usize,
+    // Goes down whenever the cell below is free, as stones do.
+    pub falls: bool,
+    // Falling objects lying on it roll off to a free side.
+    pub rounded: bool,
+    // Falling objects come to rest on it instead of sharing its cell.
+    pub supports: bool,
+    // Destroyed by falling objects landing on it.
+    pub crushable: bool,
+    // Leaves a diamond behind when crushed.
+    pub drops_diamond: bool,
+    // Moved a cell sideways by the player walking into it.
+    pub pushable: bool,
+    pub effect: Effect,
+}
+
+impl Default for TileBehavior {
+    fn default() -> TileBehavior {
+        TileBehavior {
+            walkable: true,
+            enemy_passable: true,
+            hammer_breakable: false,
+            destroyed_on_enter: false,
+            requires_key: false,
+            damage: 0,
+            falls: false,
+            rounded: false,
+            supports: false,
+            crushable: false,
+            drops_diamond: false,
+            pushable: false,
+            effect: Effect::None,
+        }
+    }
+}
+
+// Behaviour of every kind of tile, read from the tile table (assets/tiles.json).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TileRules {
+    behaviors: [TileBehavior; TILE_NAMES.len()],
+}
+
+// The table shipped with the game, for when no other one is given.
+impl Default for TileRules {
+    fn default() -> TileRules {
+        TileRules::parse(include_str!("../assets/tiles.json")).expect("bad built-in tile table")
+    }
+}
+
+impl TileRules {
+    pub fn load(path: &str) -> TileRules {
+        let source = fs::read_to_string(path).expect("Couldn't open tile table!");
+        TileRules::parse(&source).unwrap_or_else(|error| panic!("{}: {}", path, error))
+    }
+
+    // Parses a JSON object with an entry for each tile kind.
+    pub fn parse(source: &str) -> Result<TileRules, String> {
+        let mut table: HashMap<String, TileBehavior> =
+            serde_json::from_str(source).map_err(|error| error.to_string())?;
+
+        let mut behaviors = [TileBehavior::default(); TILE_NAMES.len()];
+        for (slot, name) in TILE_NAMES.iter().enumerate() {
+            behaviors[slot] = table
+                .remove(*name)
+                .ok_or_else(|| format!("no entry for `{}`", name))?;
+        }
+        if let Some(name) = table.keys().next() {
+            return Err(format!("unknown tile kind `{}`", name));
+        }
+
+        Ok(TileRules { behaviors })
+    }
+
+    // The same table with another behaviour for the tile.
+    pub fn with_behavior(mut self, tile: TileKind, behavior: TileBehavior) -> TileRules {
+        self.behaviors[tile_slot(tile)] = behavior;
+        self
+    }
+
+    pub fn behavior(&self, tile: TileKind) -> TileBehavior {
+        self.behaviors[tile_slot(tile)]
+    }
+
+    // Whether the tiles of a cell stop the player holding the given number of keys.
+    // Every tile requiring a key takes one of its own, so two doors on a cell need two.
+    pub fn blocks_player(&self, tiles: impl IntoIterator<Item = TileKind>, keys: usize) -> bool {
+        let mut locks = 0;
+        for tile in tiles {
+            let behavior = self.behavior(tile);
+            if !behavior.walkable {
+                return true;
+            }
+            if behavior.requires_key {
+                locks += 1;
+            }
+        }
+        keys < locks
+    }
+
+    pub fn blocks_enemy(&self, tile: TileKind) -> bool {
+        !self.behavior(tile).enemy_passable
+    }
+
+    pub fn breaks_with_hammer(&self, tile: TileKind) -> bool {
+        self.behavior(tile).hammer_breakable
+    }
+
+    // None for tiles the player walks into without anything happening.
+    pub fn interactable(&self, tile: TileKind) -> Option<Interactable> {
+        let behavior = self.behavior(tile);
+        (behavior.effect != Effect::None || behavior.destroyed_on_enter).then_some(Interactable {
+            effect: behavior.effect,
+            destroy_on_enter: behavior.destroyed_on_enter,
+        })
+    }
+}
@@ -0,0 +1,633 @@
+use serde::Deserialize;
+use serde::Serialize;
+use std::iter;
+
+use crate::level::Layer;
+use crate::level::LevelGrid;
+use crate::level::PatrolAxis;
+use crate::level::TileKind;
+use crate::rules::Direction;
+use crate::rules::Effect;
+use crate::rules::Interactable;
+use crate::rules::TileRules;
+
+// Game rules without Bevy: the level as a grid of cells, the objects standing on
+// it and the player. The plugins feed it the pressed keys and draw what it reports.
+
+// The game advances in ticks of fixed length; the breaks below are given in ticks.
+pub const TICK_SECONDS: f64 = 0.1;
+pub const MINIMUM_MOVE_BREAK: u64 = 1;
+pub const MINIMUM_SPACE_BREAK: u64 = 10;
+pub const MINIMUM_HAMMER_BREAK: u64 = 3;
+pub const MINIMUM_LIFE_BREAK: u64 = 20;
+// How long the hammer stays out after a hit.
+pub const HAMMER_SWING: u64 = 2;
+// How long the player pushes against a stone before it moves.
+pub const PUSH_DELAY: u64 = 2;
+
+// Enemies wait until this tick before their first step.
+const ENEMY_START_TICK: u64 = 20;
+
+pub type Cell = (i32, i32);
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ObjectId(usize);
+
+// Anything standing on the terrain: items, doors, britle walls, stones, enemies...
+#[derive(Clone, Debug)]
+pub struct Object {
+    pub kind: TileKind,
+    pub cell: Cell,
+    // What the player walking into the object gets, if anything.
+    pub interactable: Option<Interactable>,
+    patrol: Option<Patrol>,
+    // Went down a cell in the last tick, and so crushes what it lands on.
+    falling: bool,
+}
+
+#[derive(Clone, Debug)]
+struct Patrol {
+    delta: Cell,
+    speed: u64,
+    next_movement: u64,
+}
+
+// The part of the player carried between levels and written to saves.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct PlayerState {
+    pub health: usize,
+    pub diamonds: usize,
+    pub keys: usize,
+    pub dead: bool,
+}
+
+impl Default for PlayerState {
+    fn default() -> PlayerState {
+        PlayerState {
+            health: 3,
+            diamonds: 0,
+            keys: 0,
+            dead: false,
+        }
+    }
+}
+
+// Keys held down during a step.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Controls {
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+    pub hammer: bool,
+    pub load: bool,
+}
+
+impl Controls {
+    // Keys held in either of the two.
+    pub fn merge(&self, other: &Controls) -> Controls {
+        Controls {
+            up: self.up || other.up,
+            down: self.down || other.down,
+            left: self.left || other.left,
+            right: self.right || other.right,
+            hammer: self.hammer || other.hammer,
+            load: self.load || other.load,
+        }
+    }
+
+    fn pressed(&self, direction: Direction) -> bool {
+        match direction {
+            Direction::Up => self.up,
+            Direction::Down => self.down,
+            Direction::Left => self.left,
+            Direction::Right => self.right,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum GameEvent {
+    PlayerTurned(Direction),
+    PlayerMoved(Cell),
+    HammerUsed(Direction),
+    ObjectAdded(ObjectId),
+    ObjectMoved(ObjectId, Cell),
+    ObjectRemoved(ObjectId),
+    // A falling object landed on the object, which is removed.
+    ObjectCrushed(ObjectId, Cell),
+    // The player walked into an interactable object on the cell.
+    Interacted(Interactable, Cell),
+    WallBroken(Cell),
+    // Lives left after the hit.
+    PlayerDamaged(usize),
+    PlayerDied,
+    Loaded,
+}
+
+pub struct Game {
+    rules: TileRules,
+    width: i32,
+    height: i32,
+    terrain: Vec<TileKind>,
+    objects: Vec<Option<Object>>,
+    // Objects on each cell, row by row, so that finding what stands on a cell does
+    // not depend on how many objects the level has.
+    occupancy: Vec<Vec<ObjectId>>,
+    player: PlayerState,
+    saved: PlayerState,
+    // The player as they entered the level.
+    entered: PlayerState,
+    start: Cell,
+    position: Cell,
+    facing: Direction,
+    tick: u64,
+    // First ticks at which the player may do each thing again.
+    next_movement: [u64; 4],
+    next_load: u64,
+    next_hammer: u64,
+    swinging_until: u64,
+    invulnerable_until: u64,
+    // Cell of the object being pushed and the tick the pushing started.
+    pushing: Option<(Cell, u64)>,
+}
+
+impl Game {
+    // Starts the level with the player on its start tile. Britle walls become
+    // objects on grass, so that they can be broken like any other object is removed.
+    pub fn new(level: &LevelGrid, player: PlayerState, rules: TileRules) -> Game {
+        let start = (level.start.0 as i32, level.start.1 as i32);
+        let mut game = Game {
+            rules,
+            width: level.width() as i32,
+            height: level.height() as i32,
+            terrain: vec![TileKind::Empty; level.width() * level.height()],
+            objects: Vec::new(),
+            occupancy: vec![Vec::new(); level.width() * level.height()],
+            saved: player.clone(),
+            entered: player.clone(),
+            player,
+            start,
+            position: start,
+            facing: Direction::Right,
+            tick: 0,
+            next_movement: [0; 4],
+            next_load: 0,
+            next_hammer: 0,
+            swinging_until: 0,
+            invulnerable_until: 0,
+            pushing: None,
+        };
+
+        for (x, y, tile) in level.iter() {
+            let cell = (x as i32, y as i32);
+            match tile {
+                TileKind::Britle => {
+                    game.terrain[y * level.width() + x] = TileKind::Grass;
+                    game.add_object(cell, tile);
+                }
+                _ if tile.layer() == Layer::Terrain => game.terrain[y * level.width() + x] = tile,
+                _ => {
+                    game.add_object(cell, tile);
+                }
+            }
+        }
+
+        game
+    }
+
+    fn add_object(&mut self, cell: Cell, kind: TileKind) -> ObjectId {
+        let patrol = match kind {
+            TileKind::Enemy(spawn) => Some(Patrol {
+                delta: match spawn.axis {
+                    PatrolAxis::Horizontal => (spawn.dir, 0),
+                    PatrolAxis::Vertical => (0, spawn.dir),
+                },
+                speed: ticks(spawn.speed),
+                next_movement: ENEMY_START_TICK,
+            }),
+            _ => None,
+        };
+        let id = ObjectId(self.objects.len());
+        self.objects.push(Some(Object {
+            kind,
+            cell,
+            interactable: self.rules.interactable(kind),
+            patrol,
+            falling: false,
+        }));
+        let idx = self.cell_index(cell).expect("object outside the map");
+        self.occupancy[idx].push(id);
+        id
+    }
+
+    fn cell_index(&self, cell: Cell) -> Option<usize> {
+        let (x, y) = cell;
+        let in_bounds = x >= 0 && y >= 0 && x < self.width && y < self.height;
+        in_bounds.then(|| (y * self.width + x) as usize)
+    }
+
+    pub fn rules(&self) -> &TileRules {
+        &self.rules
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn player(&self) -> &PlayerState {
+        &self.player
+    }
+
+    // The player as they entered the level, before anything on it was collected.
+    pub fn entered_player(&self) -> &PlayerState {
+        &self.entered
+    }
+
+    pub fn position(&self) -> Cell {
+        self.position
+    }
+
+    pub fn facing(&self) -> Direction {
+        self.facing
+    }
+
+    // Number of ticks played on the level.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    // Whether the player was hit recently and cannot lose another life yet.
+    pub fn invulnerable(&self) -> bool {
+        self.tick < self.invulnerable_until
+    }
+
+    pub fn hammer_swinging(&self) -> bool {
+        self.tick < self.swinging_until
+    }
+
+    // Terrain tile of every cell, row by row.
+    pub fn terrain(&self) -> impl Iterator<Item = (Cell, TileKind)> + '_ {
+        let width = self.width as usize;
+        self.terrain
+            .iter()
+            .enumerate()
+            .map(move |(idx, tile)| (((idx % width) as i32, (idx / width) as i32), *tile))
+    }
+
+    pub fn terrain_at(&self, cell: Cell) -> Option<TileKind> {
+        self.cell_index(cell).map(|idx| self.terrain[idx])
+    }
+
+    // The object, unless it has been removed.
+    pub fn object(&self, id: ObjectId) -> Option<&Object> {
+        self.objects[id.0].as_ref()
+    }
+
+    // Objects still in the game.
+    pub fn objects(&self) -> impl Iterator<Item = (ObjectId, &Object)> {
+        self.objects
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, object)| object.as_ref().map(|object| (ObjectId(idx), object)))
+    }
+
+    pub fn objects_at(&self, cell: Cell) -> impl Iterator<Item = (ObjectId, &Object)> {
+        self.cell_index(cell)
+            .into_iter()
+            .flat_map(|idx| self.occupancy[idx].iter())
+            .filter_map(|id| self.objects[id.0].as_ref().map(|object| (*id, object)))
+    }
+
+    // Whether the player holding the given number of keys cannot enter the cell.
+    fn blocks_player(&self, cell: Cell, keys: usize) -> bool {
+        match self.terrain_at(cell) {
+            Some(tile) => {
+                let objects = self.objects_at(cell).map(|(_, object)| object.kind);
+                self.rules
+                    .blocks_player(iter::once(tile).chain(objects), keys)
+            }
+            None => true,
+        }
+    }
+
+    fn blocks_enemy(&self, cell: Cell) -> bool {
+        match self.terrain_at(cell) {
+            Some(tile) => {
+                self.rules.blocks_enemy(tile)
+                    || self
+                        .objects_at(cell)
+                        .any(|(_, object)| self.rules.blocks_enemy(object.kind))
+            }
+            None => true,
+        }
+    }
+
+    // Moves the player to the cell unless it is blocked (doors count as closed).
+    pub fn place_player(&mut self, cell: Cell) -> bool {
+        if self.blocks_player(cell, 0) {
+            return false;
+        }
+        self.position = cell;
+        true
+    }
+
+    fn remove(&mut self, id: ObjectId, events: &mut Vec<GameEvent>) {
+        if let Some(object) = self.objects[id.0].take() {
+            let idx = self.cell_index(object.cell).unwrap();
+            self.occupancy[idx].retain(|other| *other != id);
+        }
+        events.push(GameEvent::ObjectRemoved(id));
+    }
+
+    fn move_object(&mut self, id: ObjectId, target: Cell, events: &mut Vec<GameEvent>) {
+        let to = self
+            .cell_index(target)
+            .expect("object moved outside the map");
+        let object = self.objects[id.0].as_mut().unwrap();
+        let from = object.cell;
+        object.cell = target;
+        let from = self.cell_index(from).unwrap();
+        self.occupancy[from].retain(|other| *other != id);
+        self.occupancy[to].push(id);
+        events.push(GameEvent::ObjectMoved(id, target));
+    }
+
+    // Plays one tick with the keys held down.
+    pub fn step(&mut self, controls: &Controls) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        self.tick += 1;
+
+        if controls.load && self.next_load <= self.tick {
+            self.next_load = self.tick + MINIMUM_SPACE_BREAK;
+            self.player = self.saved.clone();
+            self.position = self.start;
+            events.push(GameEvent::Loaded);
+            events.push(GameEvent::PlayerMoved(self.start));
+            return events;
+        }
+
+        if !self.player.dead {
+            if controls.hammer && self.next_hammer <= self.tick {
+                self.next_hammer = self.tick + MINIMUM_HAMMER_BREAK;
+                self.swinging_until = self.tick + HAMMER_SWING;
+                self.use_hammer(&mut events);
+            }
+            self.move_player(controls, &mut events);
+        }
+
+        self.move_enemies(&mut events);
+        self.drop_objects(&mut events);
+
+        let damage = self
+            .objects_at(self.position)
+            .map(|(_, object)| self.rules.behavior(object.kind).damage)
+            .chain(
+                self.terrain_at(self.position)
+                    .map(|tile| self.rules.behavior(tile).damage),
+            )
+            .max()
+            .unwrap_or(0);
+        if damage > 0 {
+            self.hurt_player(damage, &mut events);
+        }
+
+        events
+    }
+
+    // Takes lives from the player, unless they were hurt less than
+    // MINIMUM_LIFE_BREAK ticks ago.
+    fn hurt_player(&mut self, lives: usize, events: &mut Vec<GameEvent>) {
+        if self.player.dead || self.invulnerable() {
+            return;
+        }
+
+        self.invulnerable_until = self.tick + MINIMUM_LIFE_BREAK;
+        self.player.health = self.player.health.saturating_sub(lives);
+        events.push(GameEvent::PlayerDamaged(self.player.health));
+        if self.player.health == 0 {
+            self.player.dead = true;
+            events.push(GameEvent::PlayerDied);
+        }
+    }
+
+    fn use_hammer(&mut self, events: &mut Vec<GameEvent>) {
+        events.push(GameEvent::HammerUsed(self.facing));
+
+        let (dx, dy) = self.facing.delta();
+        let target = (self.position.0 + dx, self.position.1 + dy);
+        let broken: Vec<ObjectId> = self
+            .objects_at(target)
+            .filter(|(_, object)| self.rules.breaks_with_hammer(object.kind))
+            .map(|(id, _)| id)
+            .collect();
+        for id in broken {
+            self.remove(id, events);
+            events.push(GameEvent::WallBroken(target));
+        }
+    }
+
+    fn move_player(&mut self, controls: &Controls, events: &mut Vec<GameEvent>) {
+        let (mut dx, mut dy) = (0, 0);
+        for (idx, direction) in Direction::ALL.into_iter().enumerate() {
+            if controls.pressed(direction) && self.next_movement[idx] <= self.tick {
+                self.next_movement[idx] = self.tick + MINIMUM_MOVE_BREAK;
+                self.facing = direction;
+                let delta = direction.delta();
+                dx += delta.0;
+                dy += delta.1;
+                events.push(GameEvent::PlayerTurned(direction));
+            }
+        }
+
+        if !Direction::ALL
+            .into_iter()
+            .any(|direction| controls.pressed(direction))
+        {
+            self.pushing = None;
+        }
+
+        let target = (self.position.0 + dx, self.position.1 + dy);
+        if (dx, dy) == (0, 0) {
+            return;
+        }
+        self.push(target, (dx, dy), events);
+        if self.blocks_player(target, self.player.keys) {
+            return;
+        }
+        self.position = target;
+        events.push(GameEvent::PlayerMoved(target));
+
+        let entered: Vec<(ObjectId, Interactable)> = self
+            .objects_at(target)
+            .filter_map(|(id, object)| Some((id, object.interactable?)))
+            .collect();
+        for (id, interactable) in entered {
+            if interactable.destroy_on_enter {
+                self.remove(id, events);
+            }
+
+            match interactable.effect {
+                Effect::AddKey => self.player.keys += 1,
+                Effect::UseKey => self.player.keys = self.player.keys.saturating_sub(1),
+                Effect::AddDiamond => self.player.diamonds += 1,
+                Effect::AddLife => self.player.health += 1,
+                Effect::Save => self.saved = self.player.clone(),
+                Effect::None | Effect::ReachExit => {}
+            }
+            events.push(GameEvent::Interacted(interactable, target));
+        }
+    }
+
+    // Objects which fall (stones) go down one cell when the cell below is free, or
+    // roll off rounded objects. The lowest ones move first, row by row from the
+    // left, so that a column of stones falls as one and every run settles alike.
+    fn drop_objects(&mut self, events: &mut Vec<GameEvent>) {
+        let mut falling: Vec<(ObjectId, Cell)> = self
+            .objects()
+            .filter(|(_, object)| self.rules.behavior(object.kind).falls)
+            .map(|(id, object)| (id, object.cell))
+            .collect();
+        falling.sort_by_key(|(_, cell)| (-cell.1, cell.0));
+
+        for (id, cell) in falling {
+            let below = (cell.0, cell.1 + 1);
+            let was_falling = self.objects[id.0].as_ref().unwrap().falling;
+            let landed_on_something = was_falling && self.crush(below, events);
+
+            let target = if landed_on_something {
+                None
+            } else {
+                self.fall_target(cell)
+            };
+            if let Some(target) = target {
+                self.move_object(id, target, events);
+            }
+            self.objects[id.0].as_mut().unwrap().falling = target == Some(below);
+        }
+    }
+
+    // A falling object lands on the cell: the player there loses a life and crushable
+    // objects are destroyed, possibly leaving a diamond. Whether anything was hit.
+    fn crush(&mut self, cell: Cell, events: &mut Vec<GameEvent>) -> bool {
+        let hit_player = cell == self.position && !self.player.dead;
+        if hit_player {
+            self.hurt_player(1, events);
+        }
+
+        let crushed: Vec<(ObjectId, TileKind)> = self
+            .objects_at(cell)
+            .filter(|(_, object)| self.rules.behavior(object.kind).crushable)
+            .map(|(id, object)| (id, object.kind))
+            .collect();
+        for (id, kind) in crushed.iter() {
+            self.remove(*id, events);
+            events.push(GameEvent::ObjectCrushed(*id, cell));
+            if self.rules.behavior(*kind).drops_diamond {
+                let diamond = self.add_object(cell, TileKind::Diamond);
+                events.push(GameEvent::ObjectAdded(diamond));
+            }
+        }
+        hit_player || !crushed.is_empty()
+    }
+
+    // Where an object falling from the cell goes this tick: down, or else to the
+    // side if it lies on a rounded object with both the side and the cell below
+    // the side free. Left is tried before right.
+    fn fall_target(&self, cell: Cell) -> Option<Cell> {
+        let below = (cell.0, cell.1 + 1);
+        if self.is_free(below) {
+            return Some(below);
+        }
+
+        let on_rounded = self
+            .objects_at(below)
+            .any(|(_, object)| self.rules.behavior(object.kind).rounded);
+        if !on_rounded {
+            return None;
+        }
+
+        [-1, 1]
+            .into_iter()
+            .map(|dx| (cell.0 + dx, cell.1))
+            .find(|side| self.is_free(*side) && self.is_free((side.0, side.1 + 1)))
+    }
+
+    // Whether nothing stands on the cell: walkable terrain, not the player and no
+    // object which supports others. Save points let falling objects onto their cell.
+    fn is_free(&self, cell: Cell) -> bool {
+        let walkable = self
+            .terrain_at(cell)
+            .is_some_and(|tile| self.rules.behavior(tile).walkable);
+        walkable
+            && cell != self.position
+            && !self
+                .objects_at(cell)
+                .any(|(_, object)| self.rules.behavior(object.kind).supports)
+    }
+
+    // Stepping sideways into a pushable object (a stone at rest) moves it on by a
+    // cell if that cell is free, once the player has kept pushing for PUSH_DELAY ticks.
+    fn push(&mut self, target: Cell, (dx, dy): Cell, events: &mut Vec<GameEvent>) {
+        let beyond = (target.0 + dx, target.1);
+        let pushed = if dy == 0 && self.is_free(beyond) {
+            self.objects_at(target)
+                .find(|(_, object)| self.rules.behavior(object.kind).pushable && !object.falling)
+                .map(|(id, _)| id)
+        } else {
+            None
+        };
+
+        let id = match pushed {
+            Some(id) => id,
+            None => {
+                self.pushing = None;
+                return;
+            }
+        };
+        let since = match self.pushing {
+            Some((cell, since)) if cell == target => since,
+            _ => self.tick,
+        };
+        if self.tick - since < PUSH_DELAY {
+            self.pushing = Some((target, since));
+            return;
+        }
+
+        self.pushing = None;
+        self.move_object(id, beyond, events);
+    }
+
+    // Enemies walk along their axis and turn back when blocked.
+    fn move_enemies(&mut self, events: &mut Vec<GameEvent>) {
+        for idx in 0..self.objects.len() {
+            let (cell, patrol) = match &self.objects[idx] {
+                Some(Object {
+                    cell,
+                    patrol: Some(patrol),
+                    ..
+                }) if patrol.next_movement <= self.tick => (*cell, patrol.clone()),
+                _ => continue,
+            };
+
+            let target = (cell.0 + patrol.delta.0, cell.1 + patrol.delta.1);
+            let blocked = self.blocks_enemy(target);
+            let patrol = self.objects[idx].as_mut().unwrap().patrol.as_mut().unwrap();
+            if blocked {
+                patrol.delta = (-patrol.delta.0, -patrol.delta.1);
+            } else {
+                patrol.next_movement = self.tick + patrol.speed;
+                self.move_object(ObjectId(idx), target, events);
+            }
+        }
+    }
+}
+
+// Number of ticks closest to the given time in seconds, at least one.
+pub fn ticks(seconds: f32) -> u64 {
+    ((seconds as f64 / TICK_SECONDS).round() as u64).max(1)
+}
@@ -0,0 +1,349 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::level::LevelGrid;
+use crate::level::TileKind;
+use crate::rules::Direction;
+use crate::rules::TileRules;
+
+// Most keys, doors, diamonds and britle walls a level can have for the solver.
+pub const MAX_REMOVABLE: usize = 128;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    // One tile in the pressed direction; two arrows pressed at once give a diagonal step.
+    Step(i32, i32),
+    // Turning to a side and using the hammer there.
+    Hammer(Direction),
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let vertical = |dy| match dy {
+            -1 => "U",
+            1 => "D",
+            _ => "",
+        };
+        let horizontal = |dx| match dx {
+            -1 => "L",
+            1 => "R",
+            _ => "",
+        };
+
+        match self {
+            Action::Step(dx, dy) => write!(f, "{}{}", vertical(*dy), horizontal(*dx)),
+            Action::Hammer(direction) => {
+                let (dx, dy) = direction.delta();
+                write!(f, "X{}{}", vertical(dy), horizontal(dx))
+            }
+        }
+    }
+}
+
+pub struct Report {
+    pub diamonds: usize,
+    pub reachable_diamonds: usize,
+    // None for levels without an exit.
+    pub exit_reachable: Option<bool>,
+    // Shortest list of actions collecting every diamond and ending on the exit.
+    pub solution: Option<Vec<Action>>,
+    // Stones which may fall or be pushed during play. The search takes them as
+    // walls, so the report only holds for sure when there are none.
+    pub movable_stones: Vec<(usize, usize)>,
+    // Why the level could not be searched, in which case nothing is reachable
+    // and there is no solution.
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct State {
+    position: (usize, usize),
+    // Bits of the removable tiles already taken, opened or broken.
+    removed: u128,
+}
+
+struct Search<'a> {
+    level: &'a LevelGrid,
+    rules: &'a TileRules,
+    // Keys, doors, diamonds and britle walls, indexed by their bit in State::removed.
+    removable: Vec<(usize, usize, TileKind)>,
+    // Bits of the removable tiles on each cell.
+    removable_at: Vec<u128>,
+    // Tiles staying on each cell for the whole game, terrain included.
+    fixed_at: Vec<Vec<TileKind>>,
+    keys: u128,
+    doors: u128,
+    diamonds: u128,
+    exits: Vec<(usize, usize)>,
+}
+
+impl<'a> Search<'a> {
+    // Fails on levels with more removable tiles than State::removed has bits.
+    fn new(level: &'a LevelGrid, rules: &'a TileRules) -> Result<Search<'a>, String> {
+        let width = level.width();
+        let mut search = Search {
+            level,
+            rules,
+            removable: Vec::new(),
+            removable_at: vec![0; width * level.height()],
+            fixed_at: vec![Vec::new(); width * level.height()],
+            keys: 0,
+            doors: 0,
+            diamonds: 0,
+            exits: Vec::new(),
+        };
+
+        for (x, y, tile) in level.iter() {
+            match tile {
+                TileKind::Key | TileKind::Door | TileKind::Diamond | TileKind::Britle => {}
+                TileKind::Exit => {
+                    search.exits.push((x, y));
+                    search.fixed_at[y * width + x].push(tile);
+                    continue;
+                }
+                _ => {
+                    search.fixed_at[y * width + x].push(tile);
+                    continue;
+                }
+            }
+
+            if search.removable.len() == MAX_REMOVABLE {
+                return Err(format!(
+                    "the level has more than {} keys, doors, diamonds and britle walls",
+                    MAX_REMOVABLE
+                ));
+            }
+            let bit = 1 << search.removable.len();
+            match tile {
+                TileKind::Key => search.keys |= bit,
+                TileKind::Door => search.doors |= bit,
+                TileKind::Diamond => search.diamonds |= bit,
+                _ => {}
+            }
+            search.removable_at[y * width + x] |= bit;
+            search.removable.push((x, y, tile));
+        }
+
+        Ok(search)
+    }
+
+    fn held_keys(&self, state: &State) -> usize {
+        let collected = (state.removed & self.keys).count_ones();
+        let used = (state.removed & self.doors).count_ones();
+        (collected - used) as usize
+    }
+
+    fn cell(&self, x: i32, y: i32) -> Option<usize> {
+        let in_bounds = x >= 0
+            && y >= 0
+            && (x as usize) < self.level.width()
+            && (y as usize) < self.level.height();
+        in_bounds.then(|| y as usize * self.level.width() + x as usize)
+    }
+
+    // Removable tiles still standing on the cell, with their bits.
+    fn standing(&self, state: &State, cell: usize) -> impl Iterator<Item = (u128, TileKind)> + '_ {
+        let bits = self.removable_at[cell] & !state.removed;
+        self.removable
+            .iter()
+            .enumerate()
+            .filter(move |(idx, _)| bits & (1 << idx) != 0)
+            .map(|(idx, (_, _, tile))| (1 << idx, *tile))
+    }
+
+    fn apply(&self, state: &State, action: Action) -> Option<State> {
+        let (x, y) = (state.position.0 as i32, state.position.1 as i32);
+        match action {
+            Action::Step(dx, dy) => {
+                let cell = self.cell(x + dx, y + dy)?;
+                let keys = self.held_keys(state);
+                let tiles = self.fixed_at[cell]
+                    .iter()
+                    .copied()
+                    .chain(self.standing(state, cell).map(|(_, tile)| tile));
+                if self.rules.blocks_player(tiles, keys) {
+                    return None;
+                }
+
+                let mut removed = state.removed;
+                for (bit, tile) in self.standing(state, cell) {
+                    if self.rules.behavior(tile).destroyed_on_enter {
+                        removed |= bit;
+                    }
+                }
+
+                Some(State {
+                    position: ((x + dx) as usize, (y + dy) as usize),
+                    removed,
+                })
+            }
+            Action::Hammer(direction) => {
+                let (dx, dy) = direction.delta();
+                let cell = self.cell(x + dx, y + dy)?;
+                let (bit, _) = self
+                    .standing(state, cell)
+                    .find(|(_, tile)| self.rules.breaks_with_hammer(*tile))?;
+
+                Some(State {
+                    position: state.position,
+                    removed: state.removed | bit,
+                })
+            }
+        }
+    }
+
+    fn is_goal(&self, state: &State) -> bool {
+        state.removed & self.diamonds == self.diamonds
+            && (self.exits.is_empty() || self.exits.contains(&state.position))
+    }
+}
+
+fn actions() -> impl Iterator<Item = Action> {
+    let steps = (-1..=1)
+        .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+        .filter(|(dx, dy)| (*dx, *dy) != (0, 0))
+        .map(|(dx, dy)| Action::Step(dx, dy));
+    steps.chain(Direction::ALL.into_iter().map(Action::Hammer))
+}
+
+// Searches every state of the level reachable from the start (player position,
+// keys held, doors opened, britle walls broken, diamonds taken) with the player
+// rules. Enemies are left out, as they can always be waited out, and so are
+// bushes, as clearing them never changes where the player can go.
+pub fn solve(level: &LevelGrid, rules: &TileRules) -> Report {
+    let search = match Search::new(level, rules) {
+        Ok(search) => search,
+        Err(error) => {
+            return Report {
+                diamonds: level
+                    .iter()
+                    .filter(|(_, _, tile)| *tile == TileKind::Diamond)
+                    .count(),
+                reachable_diamonds: 0,
+                exit_reachable: None,
+                solution: None,
+                movable_stones: movable_stones(level, rules),
+                error: Some(error),
+            }
+        }
+    };
+    let start = State {
+        position: level.start,
+        removed: 0,
+    };
+
+    let mut parents: HashMap<State, Option<(State, Action)>> = HashMap::from([(start, None)]);
+    let mut queue = VecDeque::from([start]);
+    let mut taken_diamonds = 0;
+    let mut exit_reached = false;
+    let mut goal = None;
+
+    while let Some(state) = queue.pop_front() {
+        taken_diamonds |= state.removed & search.diamonds;
+        exit_reached |= search.exits.contains(&state.position);
+        if search.is_goal(&state) {
+            goal = Some(state);
+            break;
+        }
+
+        for action in actions() {
+            if let Some(next) = search.apply(&state, action) {
+                if let Entry::Vacant(entry) = parents.entry(next) {
+                    entry.insert(Some((state, action)));
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    let solution = goal.map(|mut state| {
+        let mut path = Vec::new();
+        while let Some((previous, action)) = parents[&state] {
+            path.push(action);
+            state = previous;
+        }
+        path.reverse();
+        path
+    });
+
+    Report {
+        diamonds: search.diamonds.count_ones() as usize,
+        reachable_diamonds: if solution.is_some() {
+            search.diamonds.count_ones() as usize
+        } else {
+            taken_diamonds.count_ones() as usize
+        },
+        exit_reachable: (!search.exits.is_empty()).then(|| exit_reached || solution.is_some()),
+        solution,
+        movable_stones: movable_stones(level, rules),
+        error: None,
+    }
+}
+
+// Stones (objects which fall or are pushed) which may leave their cell: with room
+// below to fall into, a side to roll to off a rounded object, or room on both sides
+// to be pushed. A cell counts as room when nothing there supports stones, or when
+// what does may go away: things the player clears or breaks, enemies, and stones
+// which may move themselves.
+pub fn movable_stones(level: &LevelGrid, rules: &TileRules) -> Vec<(usize, usize)> {
+    let (width, height) = (level.width(), level.height());
+    let mut tiles_at = vec![Vec::new(); width * height];
+    for (x, y, tile) in level.iter() {
+        tiles_at[y * width + x].push(tile);
+    }
+    let stones: Vec<(usize, usize, TileKind)> = level
+        .iter()
+        .filter(|(_, _, tile)| rules.behavior(*tile).falls || rules.behavior(*tile).pushable)
+        .collect();
+
+    let mut movable = vec![false; width * height];
+    let has_room = |movable: &[bool], x: i32, y: i32| {
+        if x < 0 || y < 0 || x as usize >= width || y as usize >= height {
+            return false;
+        }
+        let cell = y as usize * width + x as usize;
+        tiles_at[cell].iter().all(|tile| {
+            let behavior = rules.behavior(*tile);
+            !behavior.supports
+                || behavior.destroyed_on_enter
+                || behavior.hammer_breakable
+                || behavior.crushable
+                || ((behavior.falls || behavior.pushable) && movable[cell])
+        })
+    };
+
+    // A stone moving may free others, so look again until nothing changes.
+    loop {
+        let newly_movable: Vec<usize> = stones
+            .iter()
+            .filter(|(x, y, _)| !movable[y * width + x])
+            .filter(|(x, y, tile)| {
+                let behavior = rules.behavior(*tile);
+                let room = |dx, dy| has_room(&movable, *x as i32 + dx, *y as i32 + dy);
+                let on_rounded = y + 1 < height
+                    && tiles_at[(y + 1) * width + x]
+                        .iter()
+                        .any(|below| rules.behavior(*below).rounded);
+                let falls = behavior.falls
+                    && (room(0, 1)
+                        || on_rounded && [-1, 1].into_iter().any(|dx| room(dx, 0) && room(dx, 1)));
+                falls || behavior.pushable && room(-1, 0) && room(1, 0)
+            })
+            .map(|(x, y, _)| y * width + x)
+            .collect();
+        if newly_movable.is_empty() {
+            break;
+        }
+        for cell in newly_movable {
+            movable[cell] = true;
+        }
+    }
+
+    stones
+        .into_iter()
+        .filter(|(x, y, _)| movable[y * width + x])
+        .map(|(x, y, _)| (x, y))
+        .collect()
+}
@@ -1,5 +1,6 @@
 use bevy::prelude::*;
 
+use crate::game::GridPos;
 use crate::TILE_SIZE;
 
 pub const TEXTURES_ROWS: usize = 5;
@@ -13,12 +14,13 @@ impl Plugin for TexturesPlugin {
     }
 }
 
-// Spawns sprite with image no. idx from textures.
+// Spawns sprite with image no. idx from textures on the given map cell, at depth z.
 pub fn spawn_from_textures(
     commands: &mut Commands,
     texture: &CharacterTextures,
     idx: usize,
-    translation: Vec3,
+    grid_pos: GridPos,
+    z: f32,
 ) -> Entity {
     assert!(idx < TEXTURES_ROWS * TEXTURES_COLUMNS);
 
@@ -30,15 +32,16 @@ pub fn spawn_from_textures(
             sprite,
             texture_atlas: texture.0.clone(),
             transform: Transform {
-                translation,
+                translation: grid_pos.translation(z),
                 ..Default::default()
             },
             ..Default::default()
         })
+        .insert(grid_pos)
         .id()
 }
 
-pub struct CharacterTextures(Handle<TextureAtlas>);
+pub struct CharacterTextures(pub Handle<TextureAtlas>);
 
 // Texture loading and adjusting padding.
 fn load_textures(
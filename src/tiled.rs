@@ -0,0 +1,264 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::bushes::BUSH_IDX;
+use crate::campaign::EXIT_IDX;
+use crate::diamonds::DIAMOND_IDX;
+use crate::doors::DOOR_IDX;
+use crate::keys::KEY_IDX;
+use crate::level::Layer;
+use crate::level::LevelError;
+use crate::level::LevelGrid;
+use crate::level::TileKind;
+use crate::lives::LIFE_IDX;
+use crate::save_point::SAVE_POINT_IDX;
+use crate::stones::STONE_IDX;
+use crate::worldmap::BRITLE_IDX;
+use crate::worldmap::GRASS_IDX;
+use crate::worldmap::OBSTACLE_IDX;
+
+// Tiled keeps flip flags in the highest bits of a tile id.
+const GID_MASK: u32 = 0x1fff_ffff;
+
+// Subset of the Tiled JSON map format (.tmj) used by the loader.
+// Tile layers must use the CSV encoding and the map must not be infinite.
+#[derive(Deserialize)]
+struct TiledMap {
+    width: usize,
+    height: usize,
+    tilewidth: f32,
+    tileheight: f32,
+    layers: Vec<TiledLayer>,
+    #[serde(default)]
+    tilesets: Vec<TiledTileset>,
+    #[serde(default)]
+    properties: Vec<TiledProperty>,
+}
+
+#[derive(Deserialize)]
+struct TiledLayer {
+    #[serde(rename = "type")]
+    layer_type: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    data: Vec<u32>,
+    #[serde(default)]
+    objects: Vec<TiledObject>,
+    #[serde(default)]
+    layers: Vec<TiledLayer>,
+}
+
+#[derive(Deserialize)]
+struct TiledTileset {
+    firstgid: u32,
+    #[serde(default)]
+    tiles: Vec<TiledTile>,
+}
+
+#[derive(Deserialize)]
+struct TiledTile {
+    id: u32,
+    #[serde(default, rename = "type")]
+    tile_type: String,
+    #[serde(default)]
+    class: String,
+}
+
+#[derive(Deserialize)]
+struct TiledObject {
+    #[serde(default)]
+    name: String,
+    #[serde(default, rename = "type")]
+    object_type: String,
+    #[serde(default)]
+    class: String,
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    gid: Option<u32>,
+    #[serde(default)]
+    properties: Vec<TiledProperty>,
+}
+
+#[derive(Deserialize)]
+struct TiledProperty {
+    name: String,
+    value: Value,
+}
+
+fn property<'a>(properties: &'a [TiledProperty], name: &str) -> Option<&'a Value> {
+    properties
+        .iter()
+        .find(|property| property.name == name)
+        .map(|property| &property.value)
+}
+
+// Kind of a tile of the main tileset (assets/main_assets.png), by its index in the atlas.
+fn atlas_kind(idx: usize) -> Option<TileKind> {
+    match idx {
+        OBSTACLE_IDX => Some(TileKind::Wall),
+        BRITLE_IDX => Some(TileKind::Britle),
+        GRASS_IDX => Some(TileKind::Grass),
+        BUSH_IDX => Some(TileKind::Bush),
+        KEY_IDX => Some(TileKind::Key),
+        DOOR_IDX => Some(TileKind::Door),
+        DIAMOND_IDX => Some(TileKind::Diamond),
+        LIFE_IDX => Some(TileKind::Life),
+        SAVE_POINT_IDX => Some(TileKind::SavePoint),
+        EXIT_IDX => Some(TileKind::Exit),
+        _ if STONE_IDX.contains(&idx) => Some(TileKind::Stone),
+        _ => None,
+    }
+}
+
+impl TiledMap {
+    // Kind of a tile by its global id: the class given to the tile in the tileset,
+    // or else its place in the main tileset.
+    fn tile_kind(&self, gid: u32) -> Result<TileKind, LevelError> {
+        let gid = gid & GID_MASK;
+        let tileset = self
+            .tilesets
+            .iter()
+            .filter(|tileset| tileset.firstgid <= gid)
+            .max_by_key(|tileset| tileset.firstgid)
+            .ok_or_else(|| LevelError::BadLevelFile(format!("tile {} has no tileset", gid)))?;
+        let id = gid - tileset.firstgid;
+
+        let class = tileset
+            .tiles
+            .iter()
+            .find(|tile| tile.id == id)
+            .map(|tile| {
+                if tile.class.is_empty() {
+                    &tile.tile_type
+                } else {
+                    &tile.class
+                }
+            })
+            .filter(|class| !class.is_empty());
+
+        match class {
+            Some(class) => TileKind::from_name(class).ok_or_else(|| {
+                LevelError::BadLevelFile(format!("unknown tile kind `{}` of tile {}", class, id))
+            }),
+            None => atlas_kind(id as usize)
+                .ok_or_else(|| LevelError::BadLevelFile(format!("tile {} has no kind", id))),
+        }
+    }
+
+    fn object_kind(&self, object: &TiledObject) -> Result<TileKind, LevelError> {
+        let name = [&object.class, &object.object_type, &object.name]
+            .into_iter()
+            .find(|name| !name.is_empty());
+
+        let mut kind = match (name, object.gid) {
+            (Some(name), _) => TileKind::from_name(name)
+                .ok_or_else(|| LevelError::BadLevelFile(format!("unknown tile kind `{}`", name)))?,
+            (None, Some(gid)) => self.tile_kind(gid)?,
+            (None, None) => {
+                return Err(LevelError::BadLevelFile(format!(
+                    "object at ({}, {}) has no class",
+                    object.x, object.y
+                )))
+            }
+        };
+
+        if let (TileKind::Enemy(enemy), Some(speed)) = (
+            &mut kind,
+            property(&object.properties, "speed").and_then(Value::as_f64),
+        ) {
+            enemy.speed = speed as f32;
+        }
+        Ok(kind)
+    }
+
+    // Cell under the center of an object. Tile objects are anchored at their bottom.
+    fn object_cell(&self, object: &TiledObject) -> Option<(usize, usize)> {
+        let center_x = object.x + object.width / 2.0;
+        let center_y = match object.gid {
+            Some(_) => object.y - object.height / 2.0,
+            None => object.y + object.height / 2.0,
+        };
+
+        let x = (center_x / self.tilewidth).floor();
+        let y = (center_y / self.tileheight).floor();
+        if x < 0.0 || y < 0.0 || x as usize >= self.width || y as usize >= self.height {
+            return None;
+        }
+        Some((x as usize, y as usize))
+    }
+
+    fn add_layer(&self, layer: &TiledLayer, level: &mut LevelGrid) -> Result<(), LevelError> {
+        match layer.layer_type.as_str() {
+            "tilelayer" => {
+                if layer.data.len() != self.width * self.height {
+                    return Err(LevelError::BadLevelFile(format!(
+                        "tile layer `{}` must be a CSV layer of a finite map",
+                        layer.name
+                    )));
+                }
+
+                for (idx, gid) in layer.data.iter().enumerate() {
+                    if *gid == 0 {
+                        continue;
+                    }
+
+                    let (x, y) = (idx % self.width, idx / self.width);
+                    let tile = self.tile_kind(*gid)?;
+                    if tile.layer() == Layer::Terrain {
+                        level.set_terrain(x, y, tile);
+                    } else {
+                        level.set_terrain(x, y, TileKind::Grass);
+                        level.place(x, y, tile)?;
+                    }
+                }
+            }
+            "objectgroup" => {
+                for object in layer.objects.iter() {
+                    let (x, y) = self.object_cell(object).ok_or_else(|| {
+                        LevelError::BadLevelFile(format!(
+                            "object at ({}, {}) is outside the map",
+                            object.x, object.y
+                        ))
+                    })?;
+                    level.place(x, y, self.object_kind(object)?)?;
+                }
+            }
+            "group" => {
+                for child in layer.layers.iter() {
+                    self.add_layer(child, level)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+// Parses a map exported from the Tiled editor in the JSON format. Tile layers give
+// the terrain, object layers the objects and entities, by the object class (or type,
+// or name). Enemies may have a `speed` property; `name`, `author`, `required_diamonds`
+// and `par_time` map properties fill the level metadata.
+pub fn parse_tiled(source: &str) -> Result<LevelGrid, LevelError> {
+    let map: TiledMap = serde_json::from_str(source)
+        .map_err(|error| LevelError::BadLevelFile(error.to_string()))?;
+
+    let mut level = LevelGrid::new(map.width, map.height);
+    for layer in map.layers.iter() {
+        map.add_layer(layer, &mut level)?;
+    }
+
+    let text = |name| property(&map.properties, name).and_then(Value::as_str);
+    let number = |name| property(&map.properties, name).and_then(Value::as_f64);
+    level.info.name = text("name").unwrap_or_default().to_string();
+    level.info.author = text("author").unwrap_or_default().to_string();
+    level.info.required_diamonds = number("required_diamonds").unwrap_or(0.0) as usize;
+    level.info.par_time = number("par_time").map(|par_time| par_time as f32);
+
+    level.find_start()?;
+    Ok(level)
+}
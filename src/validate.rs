@@ -0,0 +1,270 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+
+use crate::level::split_legend;
+use crate::level::Legend;
+use crate::level::LevelError;
+use crate::level::LevelGrid;
+use crate::level::TileKind;
+use crate::level_file::layered_terrain;
+use crate::level_file::parse_layered;
+use crate::rules::TileRules;
+use crate::solver::movable_stones;
+use crate::tiled::parse_tiled;
+
+// Where a problem was found: a place in a text map file, a map cell of a level
+// in another format, or the level as a whole.
+pub enum Position {
+    File { line: usize, column: usize },
+    Cell { x: usize, y: usize },
+    Level,
+}
+
+pub struct Diagnostic {
+    pub position: Position,
+    pub message: String,
+    // Warnings point at what the checks cannot be sure about; they do not make
+    // the level invalid.
+    pub warning: bool,
+}
+
+impl Diagnostic {
+    fn new(position: Position, message: String) -> Diagnostic {
+        Diagnostic {
+            position,
+            message,
+            warning: false,
+        }
+    }
+
+    fn at(level: &LevelGrid, x: usize, y: usize, message: String) -> Diagnostic {
+        let position = match level.first_line {
+            Some(first_line) => Position::File {
+                line: first_line + y,
+                column: x + 1,
+            },
+            None => Position::Cell { x, y },
+        };
+        Diagnostic::new(position, message)
+    }
+
+    fn from_error(level_first_line: Option<usize>, error: LevelError) -> Diagnostic {
+        let position = match (&error, level_first_line) {
+            (LevelError::BadLegendEntry { line }, _)
+            | (LevelError::UnknownKind { line, .. }, _) => Position::File {
+                line: *line,
+                column: 1,
+            },
+            (LevelError::DuplicateStart { x, y }, Some(first_line)) => Position::File {
+                line: first_line + y,
+                column: x + 1,
+            },
+            (LevelError::DuplicateStart { x, y }, None) => Position::Cell { x: *x, y: *y },
+            _ => Position::Level,
+        };
+        Diagnostic::new(position, error.to_string())
+    }
+
+    fn warning(mut self) -> Diagnostic {
+        self.warning = true;
+        self
+    }
+
+    // Formats the diagnostic as `path:line:column: message`, with `warning: ` before
+    // the message of warnings.
+    pub fn report(&self, path: &str) -> String {
+        let message = if self.warning {
+            format!("warning: {}", self.message)
+        } else {
+            self.message.clone()
+        };
+        match self.position {
+            Position::File { line, column } => {
+                format!("{}:{}:{}: {}", path, line, column, message)
+            }
+            Position::Cell { x, y } => format!("{}: cell ({}, {}): {}", path, x, y, message),
+            Position::Level => format!("{}: {}", path, message),
+        }
+    }
+}
+
+// Checks a level file; the format is chosen by the extension, as in LevelGrid::load.
+pub fn validate_file(path: &str, rules: &TileRules) -> io::Result<Vec<Diagnostic>> {
+    let source = fs::read_to_string(path)?;
+    if path.ends_with(".json") {
+        return Ok(validate_layered(&source, rules));
+    } else if !path.ends_with(".tmj") {
+        return Ok(validate_text(&source, rules));
+    }
+
+    Ok(match parse_tiled(&source) {
+        Ok(level) => validate_level(&level, rules),
+        Err(error) => vec![Diagnostic::from_error(None, error)],
+    })
+}
+
+// Checks rows of map symbols: that they are as wide as the first one and only use
+// symbols of the legend. Tiles are located with `position(x, y)`.
+fn validate_rows(
+    rows: &[&str],
+    legend: &Legend,
+    position: impl Fn(usize, usize) -> Position,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let width = rows.first().map(|row| row.chars().count()).unwrap_or(0);
+    for (y, row) in rows.iter().enumerate() {
+        let row_width = row.chars().count();
+        if row_width != width {
+            diagnostics.push(Diagnostic::new(
+                position(row_width.min(width), y),
+                format!("row has {} tiles, the first one has {}", row_width, width),
+            ));
+        }
+
+        for (x, symbol) in row.chars().enumerate() {
+            if !legend.contains(symbol) {
+                diagnostics.push(Diagnostic::new(
+                    position(x, y),
+                    format!("unknown symbol `{}`", symbol),
+                ));
+            }
+        }
+    }
+    diagnostics
+}
+
+// Checks a map in the text format: its shape and symbols, then the level itself.
+pub fn validate_text(source: &str, rules: &TileRules) -> Vec<Diagnostic> {
+    let (legend, map_lines, first_line) = match split_legend(source) {
+        Ok(split) => split,
+        Err(error) => return vec![Diagnostic::from_error(None, error)],
+    };
+
+    let mut diagnostics = validate_rows(&map_lines, &legend, |x, y| Position::File {
+        line: first_line + y,
+        column: x + 1,
+    });
+
+    match LevelGrid::parse(source) {
+        Ok(level) => diagnostics.extend(validate_level(&level, rules)),
+        Err(error) => diagnostics.push(Diagnostic::from_error(Some(first_line), error)),
+    }
+    diagnostics
+}
+
+// Checks a level in the layered format: the shape and symbols of its terrain rows,
+// then the level itself.
+pub fn validate_layered(source: &str, rules: &TileRules) -> Vec<Diagnostic> {
+    // A file which cannot be read is reported once, by parse_layered.
+    let mut diagnostics = match layered_terrain(source) {
+        Ok(rows) => {
+            let rows: Vec<&str> = rows.iter().map(|row| row.as_str()).collect();
+            validate_rows(&rows, &Legend::default(), |x, y| Position::Cell { x, y })
+        }
+        Err(_) => Vec::new(),
+    };
+
+    match parse_layered(source) {
+        Ok(level) => diagnostics.extend(validate_level(&level, rules)),
+        Err(error) => diagnostics.push(Diagnostic::from_error(None, error)),
+    }
+    diagnostics
+}
+
+// Checks that the level is closed by walls and that its diamonds and exits can be reached
+// from the start with the keys found on the way. What the player can walk on or break
+// with the hammer comes from the tile rules; stones count as walls, with a warning for
+// each stone which may move during play.
+pub fn validate_level(level: &LevelGrid, rules: &TileRules) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let (width, height) = (level.width(), level.height());
+
+    for (x, y, tile) in level.terrain() {
+        let on_border = x == 0 || y == 0 || x + 1 == width || y + 1 == height;
+        if on_border && tile != TileKind::Wall {
+            diagnostics.push(Diagnostic::at(
+                level,
+                x,
+                y,
+                "the map border is not closed".to_string(),
+            ));
+        }
+    }
+
+    let mut passable = vec![true; width * height];
+    for (x, y, tile) in level.iter() {
+        let behavior = rules.behavior(tile);
+        let walkable = behavior.walkable || behavior.hammer_breakable;
+        if !walkable || behavior.falls || behavior.pushable {
+            passable[y * width + x] = false;
+        }
+    }
+
+    // Flood fill from the start with every door open.
+    let mut reached = vec![false; width * height];
+    let mut queue = VecDeque::from([level.start]);
+    reached[level.start.1 * width + level.start.0] = true;
+    while let Some((x, y)) = queue.pop_front() {
+        let neighbours = [
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+        ];
+        for (next_x, next_y) in neighbours {
+            if next_x < width
+                && next_y < height
+                && passable[next_y * width + next_x]
+                && !reached[next_y * width + next_x]
+            {
+                reached[next_y * width + next_x] = true;
+                queue.push_back((next_x, next_y));
+            }
+        }
+    }
+
+    let mut keys = 0;
+    let mut doors = 0;
+    for (x, y, tile) in level.iter() {
+        let is_reached = reached[y * width + x];
+        match tile {
+            TileKind::Key if is_reached => keys += 1,
+            TileKind::Door if is_reached => doors += 1,
+            TileKind::Diamond if !is_reached => diagnostics.push(Diagnostic::at(
+                level,
+                x,
+                y,
+                "diamond cannot be reached".to_string(),
+            )),
+            TileKind::Exit if !is_reached => diagnostics.push(Diagnostic::at(
+                level,
+                x,
+                y,
+                "exit cannot be reached".to_string(),
+            )),
+            _ => {}
+        }
+    }
+
+    if keys < doors {
+        diagnostics.push(Diagnostic::new(
+            Position::Level,
+            format!("{} doors can be reached, but only {} keys", doors, keys),
+        ));
+    }
+
+    for (x, y) in movable_stones(level, rules) {
+        diagnostics.push(
+            Diagnostic::at(
+                level,
+                x,
+                y,
+                "stone can move, but is taken as a wall".to_string(),
+            )
+            .warning(),
+        );
+    }
+
+    diagnostics
+}
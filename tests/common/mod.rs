@@ -0,0 +1,100 @@
+use bevy::prelude::*;
+use std::env;
+use std::process;
+use std::thread;
+
+use diamond_rust::game::GridPos;
+use diamond_rust::game::SteppedGamePlugin;
+use diamond_rust::headless::HeadlessPlugin;
+use diamond_rust::level::LevelGrid;
+use diamond_rust::player::Player;
+use diamond_rust::player::SaveFile;
+use diamond_rust::random::GameRng;
+use diamond_rust::rules::TileRules;
+use diamond_rust::sim::Game;
+use diamond_rust::sim::PlayerState;
+use diamond_rust::GameplayPlugins;
+
+// The game without a window, playing one tick on each update of the app.
+pub struct TestGame {
+    app: App,
+}
+
+impl TestGame {
+    // Starts a level given as rows of the text map format. The first tick is
+    // played at once, as it spawns the sprites.
+    pub fn new(rows: &[&str]) -> TestGame {
+        TestGame::with_rules(rows, TileRules::default())
+    }
+
+    pub fn with_rules(rows: &[&str], rules: TileRules) -> TestGame {
+        let level = LevelGrid::parse(&rows.join("\n")).expect("bad test level");
+        let game = Game::new(&level, PlayerState::default(), rules);
+
+        // Tests run in parallel, each on its own thread and with its own save file.
+        let save_file = env::temp_dir().join(format!(
+            "diamond-rust-{}-{:?}",
+            process::id(),
+            thread::current().id()
+        ));
+
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(SaveFile(save_file))
+            .insert_resource(GameRng::new(0))
+            .insert_resource(game)
+            .insert_resource(level)
+            .add_plugin(HeadlessPlugin)
+            .add_plugin(SteppedGamePlugin)
+            .add_plugins(GameplayPlugins);
+        app.update();
+
+        TestGame { app }
+    }
+
+    // Plays the given number of ticks.
+    pub fn tick(&mut self, ticks: usize) {
+        for _ in 0..ticks {
+            self.app.update();
+        }
+    }
+
+    // Keeps the keys pressed for the given number of ticks.
+    pub fn hold(&mut self, keys: &[KeyCode], ticks: usize) {
+        let mut input = self.app.world.get_resource_mut::<Input<KeyCode>>().unwrap();
+        for key in keys {
+            input.press(*key);
+        }
+        self.tick(ticks);
+
+        let mut input = self.app.world.get_resource_mut::<Input<KeyCode>>().unwrap();
+        for key in keys {
+            input.release(*key);
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        self.app.world.get_resource::<Game>().unwrap()
+    }
+
+    // Counters shown on the player sprite.
+    pub fn player(&mut self) -> &Player {
+        let mut query = self.app.world.query::<&Player>();
+        query
+            .iter(&self.app.world)
+            .next()
+            .expect("no player sprite")
+    }
+
+    pub fn player_cell(&mut self) -> IVec2 {
+        self.cells::<Player>()[0]
+    }
+
+    // Cells of the sprites carrying the given component, in reading order.
+    pub fn cells<T: Component>(&mut self) -> Vec<IVec2> {
+        let mut query = self.app.world.query_filtered::<&GridPos, With<T>>();
+        let mut cells: Vec<IVec2> = query.iter(&self.app.world).map(|pos| pos.0).collect();
+        cells.sort_by_key(|cell| (cell.y, cell.x));
+        cells
+    }
+}
@@ -0,0 +1,348 @@
+mod common;
+
+use bevy::prelude::*;
+
+use common::TestGame;
+use diamond_rust::diamonds::DiamondDetect;
+use diamond_rust::doors::DoorDetect;
+use diamond_rust::enemy::Enemy;
+use diamond_rust::keys::KeyDetect;
+use diamond_rust::level::TileKind;
+use diamond_rust::level_file::parse_layered;
+use diamond_rust::rules::TileBehavior;
+use diamond_rust::rules::TileRules;
+use diamond_rust::save_point::SavePointDetect;
+use diamond_rust::sim::Controls;
+use diamond_rust::sim::Game;
+use diamond_rust::sim::PlayerState;
+use diamond_rust::stones::StoneSystem;
+use diamond_rust::worldmap::BritleWallDetector;
+
+#[test]
+fn player_walks_until_a_wall() {
+    let mut game = TestGame::new(&["xxxxxx", "xsooox", "xoooox", "xxxxxx"]);
+
+    game.hold(&[KeyCode::Right], 2);
+    assert_eq!(game.player_cell(), IVec2::new(3, 1));
+
+    game.hold(&[KeyCode::Right], 5);
+    assert_eq!(game.player_cell(), IVec2::new(4, 1));
+
+    game.hold(&[KeyCode::Down, KeyCode::Left], 1);
+    assert_eq!(game.player_cell(), IVec2::new(3, 2));
+}
+
+#[test]
+fn diamonds_are_collected() {
+    let mut game = TestGame::new(&["xxxxx", "xspox", "xxxxx"]);
+    assert_eq!(game.cells::<DiamondDetect>(), vec![IVec2::new(2, 1)]);
+
+    game.hold(&[KeyCode::Right], 1);
+    assert_eq!(game.player().diamonds, 1);
+    assert!(game.cells::<DiamondDetect>().is_empty());
+    // What the level is rebuilt with when its file is reloaded.
+    assert_eq!(game.game().entered_player().diamonds, 0);
+}
+
+#[test]
+fn doors_need_a_key() {
+    let mut locked = TestGame::new(&["xxxxxx", "xsodox", "xxxxxx"]);
+    locked.hold(&[KeyCode::Right], 4);
+    assert_eq!(locked.player_cell(), IVec2::new(2, 1));
+
+    let mut game = TestGame::new(&["xxxxxxx", "xskodox", "xxxxxxx"]);
+    game.hold(&[KeyCode::Right], 1);
+    assert_eq!(game.player().keys, 1);
+    assert!(game.cells::<KeyDetect>().is_empty());
+
+    game.hold(&[KeyCode::Right], 4);
+    assert_eq!(game.player_cell(), IVec2::new(5, 1));
+    assert_eq!(game.player().keys, 0);
+    assert!(game.cells::<DoorDetect>().is_empty());
+}
+
+#[test]
+fn each_door_on_a_cell_takes_a_key() {
+    let right = Controls {
+        right: true,
+        ..Controls::default()
+    };
+    let two_doors = r#"{ "kind": "door", "x": 4, "y": 1 }, { "kind": "door", "x": 4, "y": 1 }"#;
+
+    let level = format!(
+        r#"{{ "terrain": ["xxxxxxx", "xskooox", "xxxxxxx"], "objects": [{}] }}"#,
+        two_doors
+    );
+    let mut game = Game::new(
+        &parse_layered(&level).unwrap(),
+        PlayerState::default(),
+        TileRules::default(),
+    );
+    for _ in 0..5 {
+        game.step(&right);
+    }
+    assert_eq!(game.position(), (3, 1));
+    assert_eq!(game.player().keys, 1);
+
+    let level = format!(
+        r#"{{ "terrain": ["xxxxxxx", "xskkoox", "xxxxxxx"], "objects": [{}] }}"#,
+        two_doors
+    );
+    let mut game = Game::new(
+        &parse_layered(&level).unwrap(),
+        PlayerState::default(),
+        TileRules::default(),
+    );
+    for _ in 0..5 {
+        game.step(&right);
+    }
+    assert_eq!(game.position(), (5, 1));
+    assert_eq!(game.player().keys, 0);
+}
+
+#[test]
+fn hammer_breaks_britle_walls() {
+    let mut game = TestGame::new(&["xxxxx", "xs0ox", "xxxxx"]);
+
+    game.hold(&[KeyCode::Right], 3);
+    assert_eq!(game.player_cell(), IVec2::new(1, 1));
+    assert_eq!(game.cells::<BritleWallDetector>(), vec![IVec2::new(2, 1)]);
+
+    game.hold(&[KeyCode::X], 1);
+    assert!(game.cells::<BritleWallDetector>().is_empty());
+
+    game.hold(&[KeyCode::Right], 2);
+    assert_eq!(game.player_cell(), IVec2::new(3, 1));
+}
+
+#[test]
+fn enemies_hurt_the_player() {
+    let mut game = TestGame::new(&["xxxxx", "xs<ox", "xxxxx"]);
+
+    game.hold(&[KeyCode::Right], 1);
+    assert_eq!(game.player().health, 2);
+    assert!(game.game().invulnerable());
+
+    // No second hit while the player is invulnerable.
+    game.tick(5);
+    assert_eq!(game.player().health, 2);
+}
+
+#[test]
+fn loading_goes_back_to_the_last_save() {
+    let mut game = TestGame::new(&["xxxxxxx", "xsp*pox", "xxxxxxx"]);
+
+    game.hold(&[KeyCode::Right], 4);
+    assert_eq!(game.player().diamonds, 2);
+    assert_eq!(game.player_cell(), IVec2::new(5, 1));
+
+    game.hold(&[KeyCode::Space], 1);
+    assert_eq!(game.player().diamonds, 1);
+    assert_eq!(game.player_cell(), IVec2::new(1, 1));
+}
+
+#[test]
+fn stones_fall_until_something_stops_them() {
+    let mut game = TestGame::new(&[
+        "xxxxxxx", "xs@o@@x", "xoox@ox", "xooxbpx", "xooooox", "xxxxxxx",
+    ]);
+    game.tick(5);
+
+    // On a stone (with no room to roll off), on a bush, on a diamond and on the floor.
+    assert_eq!(
+        game.cells::<StoneSystem>(),
+        vec![
+            IVec2::new(4, 1),
+            IVec2::new(4, 2),
+            IVec2::new(5, 2),
+            IVec2::new(2, 4)
+        ]
+    );
+}
+
+#[test]
+fn stones_fall_through_save_points() {
+    let mut game = TestGame::new(&["xxxxx", "xs@ox", "xo*ox", "xooox", "xxxxx"]);
+    game.tick(5);
+
+    // The save point stays in place under the stone.
+    assert_eq!(game.cells::<StoneSystem>(), vec![IVec2::new(2, 3)]);
+    assert!(game.cells::<SavePointDetect>().contains(&IVec2::new(2, 2)));
+}
+
+#[test]
+fn the_start_and_the_exit_hold_stones_up() {
+    let mut game = TestGame::new(&["xxxxxxx", "xo@o@ox", "xosogox", "xxxxxxx"]);
+    game.hold(&[KeyCode::Right], 1);
+    game.tick(5);
+    assert_eq!(
+        game.cells::<StoneSystem>(),
+        vec![IVec2::new(2, 1), IVec2::new(4, 1)]
+    );
+
+    // Loading puts the player back on the start, under the stone.
+    game.hold(&[KeyCode::Space], 1);
+    game.tick(5);
+    assert_eq!(game.player_cell(), IVec2::new(2, 2));
+    assert_eq!(
+        game.cells::<StoneSystem>(),
+        vec![IVec2::new(2, 1), IVec2::new(4, 1)]
+    );
+}
+
+#[test]
+fn columns_of_stones_fall_together() {
+    let mut game = TestGame::new(&["xxxxx", "xs@ox", "xo@ox", "xooox", "xooox", "xxxxx"]);
+
+    game.tick(1);
+    assert_eq!(
+        game.cells::<StoneSystem>(),
+        vec![IVec2::new(2, 3), IVec2::new(2, 4)]
+    );
+}
+
+#[test]
+fn stones_roll_off_stones() {
+    let mut game = TestGame::new(&["xxxxxxx", "xsoooox", "xoo@oox", "xoo@oox", "xxxxxxx"]);
+    game.tick(2);
+    assert_eq!(
+        game.cells::<StoneSystem>(),
+        vec![IVec2::new(2, 3), IVec2::new(3, 3)]
+    );
+
+    // The left side is taken, so the stone rolls right.
+    let mut game = TestGame::new(&["xxxxxxx", "xsoooox", "xop@oox", "xoo@oox", "xxxxxxx"]);
+    game.tick(2);
+    assert_eq!(
+        game.cells::<StoneSystem>(),
+        vec![IVec2::new(3, 3), IVec2::new(4, 3)]
+    );
+
+    // Items are not rounded.
+    let mut game = TestGame::new(&["xxxxxxx", "xsoooox", "xoo@oox", "xoopoox", "xxxxxxx"]);
+    game.tick(2);
+    assert_eq!(game.cells::<StoneSystem>(), vec![IVec2::new(3, 2)]);
+}
+
+#[test]
+fn falling_stones_hurt_the_player() {
+    let mut game = TestGame::new(&["xxxxx", "xo@ox", "xooox", "xosox", "xxxxx"]);
+
+    game.tick(1);
+    assert_eq!(game.player().health, 2);
+
+    // The stone stops on the player and does not hurt again.
+    game.tick(30);
+    assert_eq!(game.player().health, 2);
+    assert_eq!(game.cells::<StoneSystem>(), vec![IVec2::new(2, 2)]);
+}
+
+#[test]
+fn a_stone_and_an_enemy_at_once_take_one_life() {
+    let mut game = TestGame::new(&["xxxxxx", "xoo@ox", "xoooox", "xos<ox", "xxxxxx"]);
+
+    game.hold(&[KeyCode::Right], 1);
+    assert_eq!(game.player_cell(), IVec2::new(3, 3));
+    assert_eq!(game.player().health, 2);
+    // The stone crushes the enemy sharing the player's cell as well.
+    assert!(game.cells::<Enemy>().is_empty());
+}
+
+#[test]
+fn falling_stones_crush_enemies() {
+    let mut game = TestGame::new(&["xxxxx", "xo@ox", "xooox", "xo<ox", "xsoox", "xxxxx"]);
+
+    game.tick(1);
+    assert!(game.cells::<Enemy>().is_empty());
+
+    game.tick(3);
+    assert_eq!(game.cells::<StoneSystem>(), vec![IVec2::new(2, 4)]);
+}
+
+#[test]
+fn crushed_enemies_can_drop_diamonds() {
+    let enemy = TileKind::from_name("enemy_left").unwrap();
+    let rules = TileRules::default().with_behavior(
+        enemy,
+        TileBehavior {
+            crushable: true,
+            drops_diamond: true,
+            ..TileRules::default().behavior(enemy)
+        },
+    );
+    let mut game = TestGame::with_rules(
+        &["xxxxx", "xo@ox", "xooox", "xo<ox", "xsoox", "xxxxx"],
+        rules,
+    );
+
+    game.tick(3);
+    assert!(game.cells::<Enemy>().is_empty());
+    assert_eq!(game.cells::<DiamondDetect>(), vec![IVec2::new(2, 3)]);
+    assert_eq!(game.cells::<StoneSystem>(), vec![IVec2::new(2, 2)]);
+}
+
+#[test]
+fn player_pushes_stones_after_a_delay() {
+    let mut game = TestGame::new(&["xxxxxx", "xs@oox", "xxxxxx"]);
+
+    game.hold(&[KeyCode::Right], 2);
+    assert_eq!(game.player_cell(), IVec2::new(1, 1));
+
+    game.hold(&[KeyCode::Right], 1);
+    assert_eq!(game.player_cell(), IVec2::new(2, 1));
+    assert_eq!(game.cells::<StoneSystem>(), vec![IVec2::new(3, 1)]);
+
+    // Letting go starts the delay again.
+    game.hold(&[KeyCode::Right], 2);
+    game.tick(1);
+    game.hold(&[KeyCode::Right], 2);
+    assert_eq!(game.cells::<StoneSystem>(), vec![IVec2::new(3, 1)]);
+}
+
+#[test]
+fn only_single_stones_are_pushed() {
+    let mut game = TestGame::new(&["xxxxxx", "xs@@ox", "xxxxxx"]);
+
+    game.hold(&[KeyCode::Right], 5);
+    assert_eq!(game.player_cell(), IVec2::new(1, 1));
+    assert_eq!(
+        game.cells::<StoneSystem>(),
+        vec![IVec2::new(2, 1), IVec2::new(3, 1)]
+    );
+}
+
+#[test]
+fn bushes_hold_stones_up() {
+    let mut game = TestGame::new(&["xxxxxx", "xo@oox", "xsboox", "xoooox", "xxxxxx"]);
+    game.tick(5);
+    assert_eq!(game.cells::<StoneSystem>(), vec![IVec2::new(2, 1)]);
+
+    // The stone waits on the player clearing the bush, then falls behind them.
+    game.hold(&[KeyCode::Right], 1);
+    assert_eq!(game.cells::<StoneSystem>(), vec![IVec2::new(2, 1)]);
+    game.hold(&[KeyCode::Right], 1);
+    game.tick(2);
+    assert_eq!(game.cells::<StoneSystem>(), vec![IVec2::new(2, 3)]);
+    assert_eq!(game.player().health, 3);
+}
+
+#[test]
+fn clearing_a_bush_settles_the_stones_above_the_same_way_every_time() {
+    let rows = [
+        "xxxxxxx", "xox@xox", "xox@xox", "xox@xox", "xsoboox", "xooooox", "xxxxxxx",
+    ];
+
+    for _ in 0..2 {
+        let mut game = TestGame::new(&rows);
+        game.hold(&[KeyCode::Right], 3);
+        game.tick(10);
+
+        // The lowest stone reaches the floor, the next rolls off it to the left and
+        // the last stays on top, as the player stands on the right.
+        assert_eq!(game.player_cell(), IVec2::new(4, 4));
+        assert_eq!(
+            game.cells::<StoneSystem>(),
+            vec![IVec2::new(3, 4), IVec2::new(2, 5), IVec2::new(3, 5)]
+        );
+    }
+}
@@ -0,0 +1,46 @@
+use diamond_rust::campaign::Campaign;
+use diamond_rust::campaign::CAMPAIGN_PATH;
+use diamond_rust::level::LevelGrid;
+use diamond_rust::level::TileKind;
+use diamond_rust::rules::TileBehavior;
+use diamond_rust::rules::TileRules;
+use diamond_rust::solver::solve;
+use diamond_rust::solver::MAX_REMOVABLE;
+use diamond_rust::validate::validate_level;
+
+#[test]
+fn every_campaign_level_can_be_finished() {
+    for path in Campaign::load(CAMPAIGN_PATH).levels {
+        let report = solve(&LevelGrid::load(&path), &TileRules::default());
+        assert!(report.solution.is_some(), "{} cannot be finished", path);
+    }
+}
+
+#[test]
+fn levels_too_big_for_the_solver_are_reported() {
+    let wall = "x".repeat(MAX_REMOVABLE + 4);
+    let row = format!("xs{}x", "p".repeat(MAX_REMOVABLE + 1));
+    let level = LevelGrid::parse(&[wall.as_str(), &row, &wall].join("\n")).unwrap();
+
+    let report = solve(&level, &TileRules::default());
+    assert!(report.solution.is_none());
+    assert!(report.error.is_some());
+    assert_eq!(report.diamonds, MAX_REMOVABLE + 1);
+}
+
+#[test]
+fn validation_follows_the_tile_rules() {
+    let level = LevelGrid::parse("xxxxx\nxs0px\nxxxxx").unwrap();
+    assert!(validate_level(&level, &TileRules::default()).is_empty());
+
+    let unbreakable = TileRules::default().with_behavior(
+        TileKind::Britle,
+        TileBehavior {
+            hammer_breakable: false,
+            ..TileRules::default().behavior(TileKind::Britle)
+        },
+    );
+    let diagnostics = validate_level(&level, &unbreakable);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "diamond cannot be reached");
+}
@@ -0,0 +1,46 @@
+use std::path::PathBuf;
+
+use diamond_rust::options::Options;
+
+fn parse(args: &[&str]) -> Result<Options, String> {
+    Options::parse(args.iter().map(|arg| arg.to_string()))
+}
+
+#[test]
+fn no_arguments_give_the_defaults() {
+    assert_eq!(parse(&[]), Ok(Options::default()));
+}
+
+#[test]
+fn options_are_read() {
+    let options = parse(&[
+        "--level",
+        "assets/map2.json",
+        "--save-dir",
+        "saves",
+        "--seed",
+        "42",
+        "--window",
+        "800x600",
+        "--no-inspector",
+    ])
+    .unwrap();
+
+    assert_eq!(options.level.as_deref(), Some("assets/map2.json"));
+    assert_eq!(options.save_dir, PathBuf::from("saves"));
+    assert_eq!(options.seed, Some(42));
+    assert_eq!(
+        (options.window_width, options.window_height),
+        (800.0, 600.0)
+    );
+    assert!(!options.inspector);
+}
+
+#[test]
+fn bad_options_are_reported() {
+    assert!(parse(&["--seed"]).is_err());
+    assert!(parse(&["--seed", "many"]).is_err());
+    assert!(parse(&["--window", "800"]).is_err());
+    assert!(parse(&["--ticks", "10"]).is_err());
+    assert!(parse(&["--bogus"]).is_err());
+}